@@ -0,0 +1,9 @@
+-- Ranks that unlock something (e.g. picking a rank icon) get announced when reached, with their own text.
+-- {user} and {channel} in the announcement are swapped for a mention of the user and the rank request channel
+ALTER TABLE rank_thresholds ADD COLUMN unlocks_perks INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE rank_thresholds ADD COLUMN announcement TEXT;
+
+--This used to be a hard-coded check for "Meowster" in the rank name
+UPDATE rank_thresholds
+SET unlocks_perks = 1, announcement = '{user}, you may now select any rank icon you want in {channel}!'
+WHERE role_name = 'Meowster';
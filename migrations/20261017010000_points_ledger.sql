@@ -0,0 +1,28 @@
+-- Every change to a user's points is an immutable row here; users.points is just the running sum,
+-- kept in step with it by rank_manager::record_points in the same transaction as the insert.
+-- reason is what happened (drop, clog, drop_removed, recalculation, ...), source_ref points at
+-- the row that caused it where there is one (e.g. "drop #12", "clog #34")
+CREATE TABLE IF NOT EXISTS points_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    discord_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    source_ref TEXT,
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(discord_id) REFERENCES users(discord_id)
+);
+CREATE INDEX IF NOT EXISTS idx_points_ledger_discord_id ON points_ledger(discord_id);
+
+--Corrections are new rows, never edits
+CREATE TRIGGER IF NOT EXISTS points_ledger_no_update BEFORE UPDATE ON points_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'points_ledger is append-only');
+END;
+CREATE TRIGGER IF NOT EXISTS points_ledger_no_delete BEFORE DELETE ON points_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'points_ledger is append-only');
+END;
+
+--Whatever everyone had before the ledger existed becomes their opening balance
+INSERT INTO points_ledger (discord_id, amount, reason)
+SELECT discord_id, points, 'opening_balance' FROM users WHERE points != 0;
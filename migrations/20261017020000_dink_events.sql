@@ -0,0 +1,11 @@
+-- Every Dink notification we've acted on, fingerprinted so a RuneLite retry or a duplicated
+-- webhook for the same event can be recognised and ignored instead of crediting points twice
+CREATE TABLE IF NOT EXISTS dink_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fingerprint TEXT NOT NULL,
+    account_hash TEXT NOT NULL,
+    notif_type TEXT NOT NULL,
+    player_name TEXT NOT NULL,
+    received_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_dink_events_fingerprint ON dink_events(fingerprint, received_at);
@@ -0,0 +1,15 @@
+-- Raw Dink payloads as they arrived, so events that failed to parse, weren't supported yet, or
+-- came from someone we couldn't identify can be re-processed later instead of being lost.
+-- status: pending (received, not finished), processed, duplicate, unparseable, unsupported,
+-- unknown_user, ignored. Screenshots themselves aren't kept, just their name and size
+CREATE TABLE IF NOT EXISTS dink_inbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    payload TEXT NOT NULL,
+    screenshot_name TEXT,
+    screenshot_size INTEGER,
+    status TEXT NOT NULL DEFAULT 'pending',
+    error TEXT,
+    received_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    processed_at DATETIME
+);
+CREATE INDEX IF NOT EXISTS idx_dink_inbox_status ON dink_inbox(status);
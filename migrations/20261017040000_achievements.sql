@@ -0,0 +1,51 @@
+-- Points for Dink achievement notifications (99s, quest cape, combat achievement tiers, diaries).
+-- Everything starts at 0, so these are announced but don't award anything until an admin sets
+-- a value with /achievement_points
+CREATE TABLE IF NOT EXISTS achievement_points (
+    achievement TEXT PRIMARY KEY,
+    points INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT OR IGNORE INTO achievement_points (achievement) VALUES
+    ('LEVEL_99'),
+    ('QUEST_CAPE'),
+    ('CA_EASY'),
+    ('CA_MEDIUM'),
+    ('CA_HARD'),
+    ('CA_ELITE'),
+    ('CA_MASTER'),
+    ('CA_GRANDMASTER'),
+    ('DIARY_EASY'),
+    ('DIARY_MEDIUM'),
+    ('DIARY_HARD'),
+    ('DIARY_ELITE');
+
+-- Each achievement someone has been credited for. detail is the skill for 99s and the area for
+-- diaries (empty otherwise); the unique key means each one can only ever be credited once
+CREATE TABLE IF NOT EXISTS achievements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    discord_id TEXT NOT NULL REFERENCES users(discord_id),
+    achievement TEXT NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    runescape_name TEXT,
+    points INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(discord_id, achievement, detail)
+);
+
+-- Achievement points count towards the total like drops and clogs do
+DROP VIEW IF EXISTS v_users;
+CREATE VIEW v_users as
+with droptable as (
+    select discord_id, sum(value / 100000) as drop_points, count(id) as drop_count from drops group by discord_id
+),
+clogtable as (
+    select discord_id, sum(points) as clog_points, count(item_name) as clog_count from collection_log_entries group by discord_id
+),
+achievementtable as (
+    select discord_id, sum(points) as achievement_points, count(id) as achievement_count from achievements group by discord_id
+)
+select users.discord_id, drop_points, clog_points, COALESCE(drop_points,0) + COALESCE(clog_points,0) + COALESCE(achievement_points,0) as total_points, drop_count, clog_count, achievement_points, achievement_count from users
+left join droptable on users.discord_id = droptable.discord_id
+left join clogtable on users.discord_id = clogtable.discord_id
+left join achievementtable on users.discord_id = achievementtable.discord_id;
@@ -0,0 +1,17 @@
+-- Latest kill count and personal best per boss for each RuneScape account, from Dink KILL_COUNT
+-- notifications. Keyed on the Dink account hash rather than the name so name changes don't split
+-- an account's history; runescape_name is just the last name it was seen under
+CREATE TABLE IF NOT EXISTS kill_counts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_hash TEXT NOT NULL,
+    runescape_name TEXT NOT NULL,
+    discord_id TEXT NOT NULL,
+    boss TEXT NOT NULL,
+    kill_count INTEGER NOT NULL DEFAULT 0,
+    personal_best_ms INTEGER,
+    personal_best_at DATETIME,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_hash, boss)
+);
+CREATE INDEX IF NOT EXISTS idx_kill_counts_boss ON kill_counts(boss, personal_best_ms);
+CREATE INDEX IF NOT EXISTS idx_kill_counts_discord_id ON kill_counts(discord_id);
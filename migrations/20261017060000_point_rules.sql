@@ -0,0 +1,33 @@
+-- How drops turn into points. The '*' row is the default for everything; any other row is an
+-- override for one item (matched by name, case-insensitively), where NULL means "same as the
+-- default". max_points NULL means no cap. Edited with /point_rules
+CREATE TABLE IF NOT EXISTS point_rules (
+    item_name TEXT PRIMARY KEY COLLATE NOCASE,
+    gp_per_point INTEGER CHECK (gp_per_point > 0),
+    min_value INTEGER CHECK (min_value >= 0),
+    max_points INTEGER CHECK (max_points >= 0)
+);
+
+-- What was hard-coded until now: 1 point per 100k, nothing under 100k counts
+INSERT OR IGNORE INTO point_rules (item_name, gp_per_point, min_value, max_points) VALUES ('*', 100000, 100000, NULL);
+
+-- Points are now stored per drop, so changing the rules doesn't silently change what old drops
+-- were worth (that's what /recalculate drops:True is for)
+ALTER TABLE drops ADD COLUMN points INTEGER NOT NULL DEFAULT 0;
+UPDATE drops SET points = value / 100000;
+
+DROP VIEW IF EXISTS v_users;
+CREATE VIEW v_users as
+with droptable as (
+    select discord_id, sum(points) as drop_points, count(id) as drop_count from drops group by discord_id
+),
+clogtable as (
+    select discord_id, sum(points) as clog_points, count(item_name) as clog_count from collection_log_entries group by discord_id
+),
+achievementtable as (
+    select discord_id, sum(points) as achievement_points, count(id) as achievement_count from achievements group by discord_id
+)
+select users.discord_id, drop_points, clog_points, COALESCE(drop_points,0) + COALESCE(clog_points,0) + COALESCE(achievement_points,0) as total_points, drop_count, clog_count, achievement_points, achievement_count from users
+left join droptable on users.discord_id = droptable.discord_id
+left join clogtable on users.discord_id = clogtable.discord_id
+left join achievementtable on users.discord_id = achievementtable.discord_id;
@@ -0,0 +1,23 @@
+-- The curve that turns a clog's global completion rate (%) into points, so it can be tuned from
+-- Discord with /clog_curve. Always exactly one row. The defaults are the formula that used to be
+-- hard-coded in CollectionLogManager::points:
+--   rate <= rare_rate:      rare_base * (1 / rate)^rare_exponent * rare_multiplier
+--   rate <= uncommon_rate:  uncommon_min_points at uncommon_rate, rising linearly to
+--                           uncommon_max_points at rare_rate
+--   otherwise:              common_base - rate * common_slope
+-- Items in a clamped category (and not whitelisted) never give more than clamp_ceiling
+CREATE TABLE IF NOT EXISTS clog_curve (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    rare_rate REAL NOT NULL DEFAULT 5.0,
+    rare_base REAL NOT NULL DEFAULT 100.0,
+    rare_multiplier REAL NOT NULL DEFAULT 30.0,
+    rare_exponent REAL NOT NULL DEFAULT 1.5,
+    uncommon_rate REAL NOT NULL DEFAULT 20.0,
+    uncommon_min_points REAL NOT NULL DEFAULT 200.0,
+    uncommon_max_points REAL NOT NULL DEFAULT 500.0,
+    common_base REAL NOT NULL DEFAULT 100.0,
+    common_slope REAL NOT NULL DEFAULT 0.5,
+    clamp_ceiling REAL NOT NULL DEFAULT 3000.0
+);
+
+INSERT OR IGNORE INTO clog_curve (id) VALUES (1);
@@ -0,0 +1,33 @@
+-- category_table.clamp used to be an on/off flag, with every clamped category capped at the clog
+-- curve's clamp_ceiling. It's now the cap itself, in points (0 = not clamped), so each category can
+-- have its own - e.g. clue scrolls at 1500 and gilded at 500
+UPDATE category_table
+SET clamp = COALESCE((SELECT CAST(clamp_ceiling AS INTEGER) FROM clog_curve WHERE id = 1), 3000)
+WHERE clamp = 1;
+
+-- Dropped before clog_curve is altered, since SQLite re-checks every view when a column is dropped
+-- and this one's double-quoted strings don't pass
+DROP VIEW IF EXISTS v_item_data;
+ALTER TABLE clog_curve DROP COLUMN clamp_ceiling;
+
+-- clamp is now the lowest cap of any clamped category the item is in, and point_cap is the cap that
+-- actually applies: NULL if the item is whitelisted or in no clamped category
+CREATE VIEW IF NOT EXISTS v_item_data AS WITH linkedcats as (
+                SELECT item_id, v_categories_clogs.category FROM v_categories_clogs
+            ),
+	clampedcats as (
+	SELECT linkedcats.item_id, group_concat(category_table.category, ', ') as clamped_category, MIN(clamp) as clamp
+	FROM
+	category_table
+	INNER JOIN linkedcats ON linkedcats.category=category_table.category
+	WHERE clamp > 0
+	GROUP BY item_id),
+	clogtable as (
+    SELECT collection_log_entries.item_name as item_name, count(item_name) as clog_count, points from collection_log_entries where points > 0 group by item_name order by points ASC
+)
+SELECT collection_log_items.item_id as item_id, collection_log_items.item_name as item_name, preferred_name, categories, percentage, coalesce(points,0) as highest_points, whitelist, coalesce(clog_count,0) as clog_count, coalesce(clamp,0) as clamp, coalesce(clamped_category,' ') as clamped_category,
+    CAST(CASE WHEN whitelist = 0 AND clamp > 0 THEN clamp END AS INTEGER) as point_cap
+FROM collection_log_items
+LEFT JOIN clampedcats ON clampedcats.item_id=collection_log_items.item_id
+LEFT JOIN clogtable ON clogtable.item_name=collection_log_items.item_name
+ORDER BY item_id;
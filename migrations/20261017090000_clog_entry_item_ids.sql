@@ -0,0 +1,37 @@
+-- Clogs are matched on item_id from now on. Entries from before /clog stored it (and every RuneLite
+-- one) only have a name - /clog saved the wiki's item_name, Dink the preferred_name - so look both
+-- up. Names shared by several items go to the lowest id. Anything still unmatched (the item list is
+-- loaded from the wiki at startup, so it may be empty here) is filled in by CollectionLogManager
+-- after each refresh.
+UPDATE collection_log_entries
+SET item_id = (
+    SELECT item_id FROM collection_log_items
+    WHERE collection_log_items.item_name = collection_log_entries.item_name COLLATE NOCASE
+       OR collection_log_items.preferred_name = collection_log_entries.item_name COLLATE NOCASE
+    ORDER BY item_id
+    LIMIT 1
+)
+WHERE item_id IS NULL;
+
+-- Count clogs per item_id rather than per name, so items sharing a name stop borrowing each other's
+-- clogs
+DROP VIEW IF EXISTS v_item_data;
+CREATE VIEW IF NOT EXISTS v_item_data AS WITH linkedcats as (
+                SELECT item_id, v_categories_clogs.category FROM v_categories_clogs
+            ),
+	clampedcats as (
+	SELECT linkedcats.item_id, group_concat(category_table.category, ', ') as clamped_category, MIN(clamp) as clamp
+	FROM
+	category_table
+	INNER JOIN linkedcats ON linkedcats.category=category_table.category
+	WHERE clamp > 0
+	GROUP BY item_id),
+	clogtable as (
+    SELECT collection_log_entries.item_id as item_id, count(*) as clog_count, MAX(points) as points from collection_log_entries where points > 0 AND item_id IS NOT NULL group by item_id
+)
+SELECT collection_log_items.item_id as item_id, collection_log_items.item_name as item_name, preferred_name, categories, percentage, coalesce(points,0) as highest_points, whitelist, coalesce(clog_count,0) as clog_count, coalesce(clamp,0) as clamp, coalesce(clamped_category,' ') as clamped_category,
+    CAST(CASE WHEN whitelist = 0 AND clamp > 0 THEN clamp END AS INTEGER) as point_cap
+FROM collection_log_items
+LEFT JOIN clampedcats ON clampedcats.item_id=collection_log_items.item_id
+LEFT JOIN clogtable ON clogtable.item_id=collection_log_items.item_id
+ORDER BY item_id;
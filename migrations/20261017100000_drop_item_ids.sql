@@ -0,0 +1,42 @@
+-- drops.item_id was added without anything ever filling it in (Dink drops aside). Backfill what we
+-- can from the database: other drops of the same item that do have one, then the collection log
+-- items (they use the same game ids). Anything left is matched against the price mappings by
+-- PriceManager when the bot starts.
+UPDATE drops
+SET item_id = (
+    SELECT other.item_id FROM drops AS other
+    WHERE other.item_name = drops.item_name COLLATE NOCASE AND other.item_id IS NOT NULL
+    ORDER BY other.id DESC
+    LIMIT 1
+)
+WHERE item_id IS NULL;
+
+UPDATE drops
+SET item_id = (
+    SELECT item_id FROM collection_log_items
+    WHERE collection_log_items.item_name = drops.item_name COLLATE NOCASE
+    ORDER BY item_id
+    LIMIT 1
+)
+WHERE item_id IS NULL;
+
+-- What each item id is called now: the name on its most recent drop, so drops from before an item
+-- was renamed show (and add up) under the new name
+CREATE VIEW IF NOT EXISTS v_drop_item_names AS
+SELECT item_id, item_name
+FROM drops
+WHERE item_id IS NOT NULL
+  AND id = (SELECT MAX(latest.id) FROM drops AS latest WHERE latest.item_id = drops.item_id);
+
+-- Each user's drop totals per item, by item id where there is one and by name where there isn't
+CREATE VIEW IF NOT EXISTS v_drop_totals AS
+SELECT drops.discord_id,
+       drops.item_id,
+       COALESCE(names.item_name, drops.item_name) as item_name,
+       COUNT(*) as drop_count,
+       SUM(drops.quantity) as total_quantity,
+       SUM(drops.value) as total_value,
+       MAX(drops.value) as best_value
+FROM drops
+LEFT JOIN v_drop_item_names AS names ON names.item_id = drops.item_id
+GROUP BY drops.discord_id, COALESCE(drops.item_id, drops.item_name);
@@ -0,0 +1,23 @@
+-- Last good copy of the wiki's item mapping and latest prices, so the bot can start (and keep
+-- pricing drops) while prices.runescape.wiki is down. Overwritten on every successful fetch.
+CREATE TABLE IF NOT EXISTS price_mappings (
+    item_id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    examine TEXT,
+    members INTEGER,
+    low_alch REAL,
+    high_alch REAL,
+    buy_limit INTEGER,
+    value INTEGER,
+    icon TEXT,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS latest_prices (
+    item_id INTEGER PRIMARY KEY,
+    high REAL,
+    high_time INTEGER,
+    low REAL,
+    low_time INTEGER,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,20 @@
+-- Hourly average prices for every item, from the wiki's /1h feed. Drops are valued at the average of
+-- the last few hours of these (a time-weighted average, which one odd trade can't swing like the
+-- instantaneous price could) and point at the newest snapshot that went into it, so a disputed
+-- value can be checked with /price_audit. window_start is the feed's timestamp for the hour.
+CREATE TABLE IF NOT EXISTS price_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    item_id INTEGER NOT NULL,
+    avg_high REAL,
+    avg_low REAL,
+    high_volume INTEGER NOT NULL DEFAULT 0,
+    low_volume INTEGER NOT NULL DEFAULT 0,
+    window_start INTEGER NOT NULL,
+    taken_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(item_id, window_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_snapshots_item_taken ON price_snapshots(item_id, taken_at);
+
+-- NULL for drops valued some other way (the RuneLite tracker's own value, or no snapshots yet)
+ALTER TABLE drops ADD COLUMN price_snapshot_id INTEGER REFERENCES price_snapshots(id);
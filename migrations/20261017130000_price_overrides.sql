@@ -0,0 +1,9 @@
+-- Prices set by hand for items the market can't be trusted on (too thinly traded, or being pumped).
+-- An override wins over the market price and the manipulation guard for every new drop of the item.
+CREATE TABLE IF NOT EXISTS price_overrides (
+    item_id INTEGER PRIMARY KEY,
+    item_name TEXT NOT NULL,
+    price INTEGER NOT NULL,
+    set_by TEXT NOT NULL,
+    set_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
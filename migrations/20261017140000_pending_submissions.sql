@@ -0,0 +1,26 @@
+-- Manual /drop and /clog submissions worth enough to need a mod's say-so (APPROVAL_VALUE_THRESHOLD /
+-- APPROVAL_POINTS_THRESHOLD). Nothing is credited until one is approved from the buttons on its
+-- message in the mod channel; value and points are what they were worth when submitted.
+CREATE TABLE IF NOT EXISTS pending_submissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL CHECK(kind IN ('drop', 'clog')),
+    discord_id TEXT NOT NULL,
+    user_name TEXT NOT NULL,
+    item_name TEXT NOT NULL,
+    item_id INTEGER,
+    quantity INTEGER NOT NULL DEFAULT 1,
+    value INTEGER NOT NULL DEFAULT 0,
+    points INTEGER NOT NULL,
+    price_snapshot_id INTEGER REFERENCES price_snapshots(id),
+    -- Anything the mods should know, e.g. why the price wasn't trusted
+    note TEXT,
+    -- Where it was submitted from, to tell the submitter if they can't be DMed
+    channel_id TEXT NOT NULL,
+    message_id TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'approved', 'denied')),
+    reviewed_by TEXT,
+    reviewed_at DATETIME,
+    submitted_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_submissions_status ON pending_submissions(status, discord_id);
@@ -0,0 +1,5 @@
+-- Link to the screenshot a manual /drop or /clog was submitted with, re-uploaded to the bot log
+-- channel so it stays up even if the original message goes. NULL if none was given.
+ALTER TABLE drops ADD COLUMN evidence_url TEXT;
+ALTER TABLE collection_log_entries ADD COLUMN evidence_url TEXT;
+ALTER TABLE pending_submissions ADD COLUMN evidence_url TEXT;
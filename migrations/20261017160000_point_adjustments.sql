@@ -0,0 +1,22 @@
+-- Points a mod gives or takes with /admin_points_adjust don't come from a drop, clog or
+-- achievement - they only exist as 'adjustment' rows in points_ledger - so v_users has to count
+-- them too, or /audit_points would flag (and "fix") every adjusted user
+DROP VIEW IF EXISTS v_users;
+CREATE VIEW v_users as
+with droptable as (
+    select discord_id, sum(points) as drop_points, count(id) as drop_count from drops group by discord_id
+),
+clogtable as (
+    select discord_id, sum(points) as clog_points, count(item_name) as clog_count from collection_log_entries group by discord_id
+),
+achievementtable as (
+    select discord_id, sum(points) as achievement_points, count(id) as achievement_count from achievements group by discord_id
+),
+adjustmenttable as (
+    select discord_id, sum(amount) as adjustment_points from points_ledger where reason = 'adjustment' group by discord_id
+)
+select users.discord_id, drop_points, clog_points, COALESCE(drop_points,0) + COALESCE(clog_points,0) + COALESCE(achievement_points,0) + COALESCE(adjustment_points,0) as total_points, drop_count, clog_count, achievement_points, achievement_count, adjustment_points from users
+left join droptable on users.discord_id = droptable.discord_id
+left join clogtable on users.discord_id = clogtable.discord_id
+left join achievementtable on users.discord_id = achievementtable.discord_id
+left join adjustmenttable on users.discord_id = adjustmenttable.discord_id;
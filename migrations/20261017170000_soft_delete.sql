@@ -0,0 +1,73 @@
+-- /drop_remove, /clog_remove and their admin versions used to DELETE the row, so a mistaken removal
+-- couldn't be brought back. Removed entries are now kept, marked with when, by whom and why, and
+-- everything that counts drops or clogs skips them. /undo clears the marks again.
+ALTER TABLE drops ADD COLUMN deleted_at DATETIME;
+ALTER TABLE drops ADD COLUMN deleted_by TEXT;
+ALTER TABLE drops ADD COLUMN delete_reason TEXT;
+
+ALTER TABLE collection_log_entries ADD COLUMN deleted_at DATETIME;
+ALTER TABLE collection_log_entries ADD COLUMN deleted_by TEXT;
+ALTER TABLE collection_log_entries ADD COLUMN delete_reason TEXT;
+
+DROP VIEW IF EXISTS v_users;
+CREATE VIEW v_users as
+with droptable as (
+    select discord_id, sum(points) as drop_points, count(id) as drop_count from drops where deleted_at is null group by discord_id
+),
+clogtable as (
+    select discord_id, sum(points) as clog_points, count(item_name) as clog_count from collection_log_entries where deleted_at is null group by discord_id
+),
+achievementtable as (
+    select discord_id, sum(points) as achievement_points, count(id) as achievement_count from achievements group by discord_id
+),
+adjustmenttable as (
+    select discord_id, sum(amount) as adjustment_points from points_ledger where reason = 'adjustment' group by discord_id
+)
+select users.discord_id, drop_points, clog_points, COALESCE(drop_points,0) + COALESCE(clog_points,0) + COALESCE(achievement_points,0) + COALESCE(adjustment_points,0) as total_points, drop_count, clog_count, achievement_points, achievement_count, adjustment_points from users
+left join droptable on users.discord_id = droptable.discord_id
+left join clogtable on users.discord_id = clogtable.discord_id
+left join achievementtable on users.discord_id = achievementtable.discord_id
+left join adjustmenttable on users.discord_id = adjustmenttable.discord_id;
+
+DROP VIEW IF EXISTS v_drop_totals;
+DROP VIEW IF EXISTS v_drop_item_names;
+
+CREATE VIEW IF NOT EXISTS v_drop_item_names AS
+SELECT item_id, item_name
+FROM drops
+WHERE item_id IS NOT NULL
+  AND id = (SELECT MAX(latest.id) FROM drops AS latest WHERE latest.item_id = drops.item_id AND latest.deleted_at IS NULL);
+
+CREATE VIEW IF NOT EXISTS v_drop_totals AS
+SELECT drops.discord_id,
+       drops.item_id,
+       COALESCE(names.item_name, drops.item_name) as item_name,
+       COUNT(*) as drop_count,
+       SUM(drops.quantity) as total_quantity,
+       SUM(drops.value) as total_value,
+       MAX(drops.value) as best_value
+FROM drops
+LEFT JOIN v_drop_item_names AS names ON names.item_id = drops.item_id
+WHERE drops.deleted_at IS NULL
+GROUP BY drops.discord_id, COALESCE(drops.item_id, drops.item_name);
+
+DROP VIEW IF EXISTS v_item_data;
+CREATE VIEW IF NOT EXISTS v_item_data AS WITH linkedcats as (
+                SELECT item_id, v_categories_clogs.category FROM v_categories_clogs
+            ),
+	clampedcats as (
+	SELECT linkedcats.item_id, group_concat(category_table.category, ', ') as clamped_category, MIN(clamp) as clamp
+	FROM
+	category_table
+	INNER JOIN linkedcats ON linkedcats.category=category_table.category
+	WHERE clamp > 0
+	GROUP BY item_id),
+	clogtable as (
+    SELECT collection_log_entries.item_id as item_id, count(*) as clog_count, MAX(points) as points from collection_log_entries where points > 0 AND item_id IS NOT NULL AND deleted_at IS NULL group by item_id
+)
+SELECT collection_log_items.item_id as item_id, collection_log_items.item_name as item_name, preferred_name, categories, percentage, coalesce(points,0) as highest_points, whitelist, coalesce(clog_count,0) as clog_count, coalesce(clamp,0) as clamp, coalesce(clamped_category,' ') as clamped_category,
+    CAST(CASE WHEN whitelist = 0 AND clamp > 0 THEN clamp END AS INTEGER) as point_cap
+FROM collection_log_items
+LEFT JOIN clampedcats ON clampedcats.item_id=collection_log_items.item_id
+LEFT JOIN clogtable ON clogtable.item_id=collection_log_items.item_id
+ORDER BY item_id;
@@ -0,0 +1,35 @@
+-- Who can use each staff command: 'moderator' (anyone with MODERATOR_ROLE_ID, plus admins) or
+-- 'admin' (ADMIN_ROLE_ID, or the server's Administrator permission). Commands that aren't listed
+-- are open to everyone. `approval` is the Approve/Deny buttons on queued submissions. Checked in
+-- handle_interaction and edited with /command_permissions
+CREATE TABLE IF NOT EXISTS command_permissions (
+    command TEXT PRIMARY KEY,
+    tier TEXT NOT NULL CHECK (tier IN ('moderator', 'admin'))
+);
+
+-- Day-to-day upkeep goes to moderators; anything that rescores everyone, changes how points are
+-- worked out or hands out points directly stays with admins
+INSERT OR IGNORE INTO command_permissions (command, tier) VALUES
+    ('clamp', 'moderator'),
+    ('unclamp', 'moderator'),
+    ('whitelist', 'moderator'),
+    ('unwhitelist', 'moderator'),
+    ('price_audit', 'moderator'),
+    ('rank_list', 'moderator'),
+    ('admin_drop_add', 'moderator'),
+    ('admin_drop_remove', 'moderator'),
+    ('admin_clog_add', 'moderator'),
+    ('admin_clog_remove', 'moderator'),
+    ('approval', 'moderator'),
+    ('recalculate', 'admin'),
+    ('point_rules', 'admin'),
+    ('price_override', 'admin'),
+    ('clog_curve', 'admin'),
+    ('audit_points', 'admin'),
+    ('admin_points_adjust', 'admin'),
+    ('rank_add', 'admin'),
+    ('rank_remove', 'admin'),
+    ('rank_edit', 'admin'),
+    ('dink_replay', 'admin'),
+    ('achievement_points', 'admin'),
+    ('command_permissions', 'admin');
@@ -0,0 +1,8 @@
+-- Which dink_inbox entry each Dink drop and collection log entry came from, so a forced replay
+-- can tell whether the event was already credited. Entries recorded before this (and everything
+-- not from Dink) have none.
+ALTER TABLE drops ADD COLUMN dink_inbox_id INTEGER REFERENCES dink_inbox(id);
+ALTER TABLE collection_log_entries ADD COLUMN dink_inbox_id INTEGER REFERENCES dink_inbox(id);
+
+CREATE INDEX IF NOT EXISTS idx_drops_dink_inbox_id ON drops(dink_inbox_id);
+CREATE INDEX IF NOT EXISTS idx_collection_log_entries_dink_inbox_id ON collection_log_entries(dink_inbox_id);
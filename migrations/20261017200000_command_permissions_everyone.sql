@@ -0,0 +1,12 @@
+-- Staff commands now default to admin-only in code when they have no row here, so a missing row
+-- can't open one up to everyone. Opening a staff command up has to be recorded instead, as
+-- 'everyone'. SQLite can't change a CHECK constraint in place, so the table is rebuilt.
+CREATE TABLE command_permissions_new (
+    command TEXT PRIMARY KEY,
+    tier TEXT NOT NULL CHECK (tier IN ('everyone', 'moderator', 'admin'))
+);
+
+INSERT INTO command_permissions_new (command, tier) SELECT command, tier FROM command_permissions;
+
+DROP TABLE command_permissions;
+ALTER TABLE command_permissions_new RENAME TO command_permissions;
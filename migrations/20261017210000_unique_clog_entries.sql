@@ -0,0 +1,20 @@
+-- Nothing stopped two collection log entries for the same item being recorded for someone (e.g. a
+-- Dink event and /clog landing at the same time - both checked for an existing entry before
+-- either inserted). Any live duplicates are removed like /clog_remove would, keeping the first,
+-- and a unique index stops it happening again. Removed entries still count in users.points until
+-- `migrate --reconcile --fix` is run.
+UPDATE collection_log_entries
+SET deleted_at = CURRENT_TIMESTAMP,
+    deleted_by = 'migration',
+    delete_reason = 'duplicate entry'
+WHERE deleted_at IS NULL
+  AND item_id IS NOT NULL
+  AND id > (
+    SELECT MIN(first.id) FROM collection_log_entries AS first
+    WHERE first.discord_id = collection_log_entries.discord_id
+      AND first.item_id = collection_log_entries.item_id
+      AND first.deleted_at IS NULL
+  );
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_collection_log_entries_live_item
+ON collection_log_entries(discord_id, item_id) WHERE deleted_at IS NULL;
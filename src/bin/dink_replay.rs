@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use dotenvy::dotenv;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::env;
+use tracing::{error, info, warn};
+
+// Replays stored Dink payloads through the running bot. Processing needs the bot's Discord
+// connection, so this only reads the inbox itself and asks the bot (over its replay listener,
+// which only answers on 127.0.0.1 and needs DINK_REPLAY_TOKEN) to do the actual replaying.
+//
+//   dink_replay --list              list events that didn't go through
+//   dink_replay 12 15               replay inbox entries 12 and 15
+//   dink_replay --failed            replay everything that didn't go through
+//   dink_replay --force 12          replay 12 even if it was already processed (as long as
+//                                   nothing it credited is still recorded)
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize environment variables
+    dotenv()?;
+
+    // Initialize logging
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let list = args.iter().any(|arg| arg == "--list");
+    let failed = args.iter().any(|arg| arg == "--failed");
+    let force = args.iter().any(|arg| arg == "--force");
+    let mut ids = args.iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .map(|arg| arg.parse::<i64>().map_err(|_| anyhow::anyhow!("Not an inbox id: {}", arg)))
+        .collect::<Result<Vec<i64>>>()?;
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    if list || failed {
+        let entries = sqlx::query!(
+            r#"SELECT id as "id!", status, error, received_at,
+                      json_extract(payload, '$.type') as "notif_type: String",
+                      json_extract(payload, '$.playerName') as "player_name: String"
+               FROM dink_inbox
+               WHERE status NOT IN ('processed', 'duplicate', 'pending')
+               ORDER BY id ASC"#
+        )
+        .fetch_all(&db)
+        .await?;
+
+        if entries.is_empty() {
+            info!("Nothing waiting to be replayed");
+        }
+        for entry in &entries {
+            info!(
+                "#{} {} from {} at {}: {}{}",
+                entry.id,
+                entry.notif_type.as_deref().unwrap_or("?"),
+                entry.player_name.as_deref().unwrap_or("?"),
+                entry.received_at.map(|t| t.to_string()).unwrap_or_default(),
+                entry.status,
+                entry.error.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default()
+            );
+        }
+
+        if list {
+            return Ok(());
+        }
+        ids.extend(entries.iter().map(|entry| entry.id));
+    }
+
+    if ids.is_empty() {
+        bail!("Nothing to replay - pass inbox ids, --failed, or --list to see what's there");
+    }
+
+    let token = env::var("DINK_REPLAY_TOKEN").expect("DINK_REPLAY_TOKEN must be set");
+    let base_url = env::var("DINK_REPLAY_URL").unwrap_or_else(|_| {
+        format!("http://127.0.0.1:{}", env::var("DINK_REPLAY_PORT").unwrap_or_else(|_| "3001".to_string()))
+    });
+    let client = reqwest::Client::new();
+
+    for id in ids {
+        let url = format!("{}/dink/replay/{}{}", base_url.trim_end_matches('/'), id, if force {"?force=true"} else {""});
+        let response = match client.post(&url).bearer_auth(&token).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("#{}: couldn't reach the bot at {}: {}", id, base_url, e);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        match status {
+            reqwest::StatusCode::OK => info!("#{}: {}", id, body),
+            reqwest::StatusCode::NOT_FOUND => warn!("#{}: not found", id),
+            reqwest::StatusCode::UNAUTHORIZED => error!("#{}: DINK_REPLAY_TOKEN doesn't match the bot's (or the bot doesn't have one set)", id),
+            reqwest::StatusCode::CONFLICT if force => warn!("#{}: {}", id, body),
+            reqwest::StatusCode::CONFLICT => warn!("#{}: {} - pass --force to replay anyway", id, body),
+            _ => error!("#{}: bot answered {}", id, status),
+        }
+    }
+
+    Ok(())
+}
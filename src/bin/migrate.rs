@@ -1,10 +1,14 @@
 use anyhow::Result;
 use dotenvy::dotenv;
 use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
 use std::env;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber;
 
+#[path = "../ledger.rs"]
+mod ledger;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize environment variables
@@ -13,11 +17,16 @@ async fn main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt::init();
 
+    // --reconcile lists everyone whose points don't add up, --reconcile --fix also corrects them
+    let args: Vec<String> = env::args().skip(1).collect();
+    let reconcile = args.iter().any(|arg| arg == "--reconcile");
+    let fix = args.iter().any(|arg| arg == "--fix");
+
     info!("Running database migrations...");
-    
+
     // Get database URL
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    
+
     // Create database connection pool
     let db = SqlitePoolOptions::new()
         .max_connections(1)
@@ -28,6 +37,78 @@ async fn main() -> Result<()> {
     sqlx::migrate!().run(&db).await?;
 
     info!("Migrations completed successfully!");
-    
+
+    if reconcile {
+        reconcile_points(&db, fix).await?;
+    } else if fix {
+        warn!("--fix does nothing without --reconcile");
+    }
+
+    Ok(())
+}
+
+// The same audit and ledger write as /audit_points: users.points against the total v_users
+// recomputes from drops, collection log entries, achievements and mod adjustments. There's no
+// Discord connection here, so rank changes from a fix are only logged to the console - the bot
+// won't announce them.
+async fn reconcile_points(db: &SqlitePool, fix: bool) -> Result<()> {
+    let discrepancies = ledger::audit_points(db).await?;
+
+    if discrepancies.is_empty() {
+        info!("Everyone's points match their drops and collection log entries");
+        return Ok(());
+    }
+
+    info!("{} users don't match their drops and collection log entries", discrepancies.len());
+
+    for user in discrepancies {
+        let point_delta = user.expected_points - user.stored_points;
+        info!(
+            "{}: stored {}, expected {} ({} from drops + {} from clogs + {} from achievements + {} from adjustments), off by {}{}",
+            user.discord_id,
+            user.stored_points,
+            user.expected_points,
+            user.drop_points,
+            user.clog_points,
+            user.achievement_points,
+            user.adjustment_points,
+            if point_delta.is_positive() {"+"} else {""},
+            point_delta
+        );
+
+        if fix {
+            let mut tx = db.begin().await?;
+            ledger::record_points(&mut tx, &user.discord_id, point_delta, "reconcile", None).await?;
+            tx.commit().await?;
+
+            let old_rank = rank_at(db, user.stored_points).await?;
+            let new_rank = rank_at(db, user.expected_points).await?;
+            if old_rank != new_rank {
+                info!(
+                    "{}: rank {} -> {} (roles will need updating by hand)",
+                    user.discord_id,
+                    old_rank.as_deref().unwrap_or("Unranked"),
+                    new_rank.as_deref().unwrap_or("Unranked")
+                );
+            }
+        }
+    }
+
+    if fix {
+        info!("Reconciliation complete!");
+    } else {
+        info!("Run with --reconcile --fix to correct them");
+    }
+
     Ok(())
-} 
\ No newline at end of file
+}
+
+async fn rank_at(db: &SqlitePool, points: i64) -> Result<Option<String>> {
+    Ok(sqlx::query!(
+        "SELECT role_name FROM rank_thresholds WHERE points <= ? ORDER BY points DESC LIMIT 1",
+        points
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|rank| rank.role_name))
+}
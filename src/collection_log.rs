@@ -21,6 +21,162 @@ pub struct CollectionLogItem {
     //pub release_date: String,
 }
 
+/// The curve clog points are worked out with, from the clog_curve table (see its migration for
+/// how the numbers fit together). Tuned with /clog_curve. Caps for clamped categories are set per
+/// category with /clamp, not here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClogCurve {
+    pub rare_rate: f64,
+    pub rare_base: f64,
+    pub rare_multiplier: f64,
+    pub rare_exponent: f64,
+    pub uncommon_rate: f64,
+    pub uncommon_min_points: f64,
+    pub uncommon_max_points: f64,
+    pub common_base: f64,
+    pub common_slope: f64,
+}
+
+impl Default for ClogCurve {
+    fn default() -> Self {
+        Self {
+            rare_rate: 5.0,
+            rare_base: 100.0,
+            rare_multiplier: 30.0,
+            rare_exponent: 1.5,
+            uncommon_rate: 20.0,
+            uncommon_min_points: 200.0,
+            uncommon_max_points: 500.0,
+            common_base: 100.0,
+            common_slope: 0.5,
+        }
+    }
+}
+
+impl ClogCurve {
+    pub async fn load(db: &SqlitePool) -> Result<Self> {
+        let curve = sqlx::query_as!(
+            ClogCurve,
+            "SELECT rare_rate, rare_base, rare_multiplier, rare_exponent, uncommon_rate, uncommon_min_points,
+                    uncommon_max_points, common_base, common_slope
+             FROM clog_curve WHERE id = 1"
+        )
+        .fetch_optional(db)
+        .await?;
+        Ok(curve.unwrap_or_default())
+    }
+
+    pub async fn save<'e>(&self, db: impl sqlx::SqliteExecutor<'e>) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO clog_curve (id, rare_rate, rare_base, rare_multiplier, rare_exponent, uncommon_rate,
+                                     uncommon_min_points, uncommon_max_points, common_base, common_slope)
+             VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                 rare_rate = excluded.rare_rate, rare_base = excluded.rare_base,
+                 rare_multiplier = excluded.rare_multiplier, rare_exponent = excluded.rare_exponent,
+                 uncommon_rate = excluded.uncommon_rate, uncommon_min_points = excluded.uncommon_min_points,
+                 uncommon_max_points = excluded.uncommon_max_points, common_base = excluded.common_base,
+                 common_slope = excluded.common_slope",
+            self.rare_rate,
+            self.rare_base,
+            self.rare_multiplier,
+            self.rare_exponent,
+            self.uncommon_rate,
+            self.uncommon_min_points,
+            self.uncommon_max_points,
+            self.common_base,
+            self.common_slope
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Catches curves that would divide by zero, put the tiers in the wrong order, give negative
+    /// points or make an item worth more the more people have it (within a tier - the rare tier
+    /// is allowed to start below uncommon_max_points, as the default curve does)
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let fields = [
+            ("rare_rate", self.rare_rate),
+            ("rare_base", self.rare_base),
+            ("rare_multiplier", self.rare_multiplier),
+            ("rare_exponent", self.rare_exponent),
+            ("uncommon_rate", self.uncommon_rate),
+            ("uncommon_min_points", self.uncommon_min_points),
+            ("uncommon_max_points", self.uncommon_max_points),
+            ("common_base", self.common_base),
+            ("common_slope", self.common_slope),
+        ];
+        if let Some((name, _)) = fields.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("{} has to be a number", name));
+        }
+        if self.rare_rate <= 0.0 {
+            return Err("rare_rate has to be above 0%".to_string());
+        }
+        if self.uncommon_rate <= self.rare_rate {
+            return Err("uncommon_rate has to be above rare_rate".to_string());
+        }
+        if self.rare_base < 0.0 || self.rare_multiplier < 0.0 {
+            return Err("rare_base and rare_multiplier can't be negative".to_string());
+        }
+        if self.rare_exponent < 0.0 {
+            return Err("rare_exponent can't be negative - rarer items would be worth less".to_string());
+        }
+        if self.uncommon_min_points < 0.0 {
+            return Err("uncommon_min_points can't be negative".to_string());
+        }
+        if self.uncommon_max_points < self.uncommon_min_points {
+            return Err("uncommon_max_points can't be below uncommon_min_points".to_string());
+        }
+        if self.common_slope < 0.0 {
+            return Err("common_slope can't be negative - commoner items would be worth more".to_string());
+        }
+        if self.common_base - 100.0 * self.common_slope < 0.0 {
+            return Err("common_base has to be at least 100 × common_slope, or the commonest items would be worth negative points".to_string());
+        }
+        Ok(())
+    }
+
+    /// `point_cap` is the item's cap from a clamped category, if it has one
+    pub fn points(&self, completion_rate: f64, point_cap: Option<i64>) -> i64 {
+        // Multi-tiered point calculation
+        let points = if completion_rate <= self.rare_rate {
+            // Tier 3: Mega-rare items (≤5% by default)
+            // 5% -> 500 points
+            // 3% -> 1000 points
+            // 1% -> 15000 points
+            // 0.5% -> 30000 points
+            let rarity_multiplier = (1.0 / completion_rate).powf(self.rare_exponent) * self.rare_multiplier;
+            self.rare_base * rarity_multiplier
+        } else if completion_rate <= self.uncommon_rate {
+            // Tier 2: Moderately rare items (5-20% by default)
+            // Linear interpolation between:
+            // 20% -> 200 points
+            // 5% -> 500 points
+            let progress = (self.uncommon_rate - completion_rate) / (self.uncommon_rate - self.rare_rate); // 0 to 1 scale
+            self.uncommon_min_points + (progress * (self.uncommon_max_points - self.uncommon_min_points))
+        } else {
+            // Tier 1: Common items (>20% by default)
+            // Simple linear scaling
+            self.common_base - (completion_rate * self.common_slope)
+        };
+
+        let points = if let Some(cap) = point_cap {
+            points.clamp(0.0, cap as f64)
+        } else {
+            points
+        };
+        points.round() as i64
+    }
+}
+
+/// A collection log item someone asked for by id or name
+pub struct ClogItemMatch {
+    pub item_id: i64,
+    pub item_name: String,
+    pub categories: String,
+}
+
 pub struct CollectionLogManager<> {
     db: SqlitePool,
 }
@@ -247,24 +403,31 @@ impl CollectionLogManager<> {
         .execute(db)
         .await?;
 
+        // Give clogs that were only recorded by name their item id, now that the items are loaded
+        let backfilled = sqlx::query!(
+            "UPDATE collection_log_entries
+            SET item_id = (
+                SELECT item_id FROM collection_log_items
+                WHERE collection_log_items.item_name = collection_log_entries.item_name COLLATE NOCASE
+                   OR collection_log_items.preferred_name = collection_log_entries.item_name COLLATE NOCASE
+                ORDER BY item_id
+                LIMIT 1
+            )
+            WHERE item_id IS NULL"
+        )
+        .execute(db)
+        .await?
+        .rows_affected();
+        if backfilled > 0 {
+            info!("Matched {} collection log entries to their item id", backfilled);
+        }
+
         info!("Initialized collection log with {} items", items.len());
         Ok(items.len().try_into().unwrap())
     }
 
-    pub async fn calculate_points(&self, item_name: &str) -> Option<i64> {
-        let item_record = sqlx::query!(
-            "SELECT * FROM v_item_data WHERE item_name LIKE '%' || ? || '%' ORDER BY item_id",
-            item_name
-        )
-        .fetch_one(&self.db)
-        .await
-        .ok()?;
-        
-        Some(Self::points(item_record.percentage, item_record.whitelist, item_record.clamp).await?)
-    }
-
-    //For Dink we do this by item id instead because it's more reliable
-    pub async fn calculate_points_dink(&self, item_id: i64) -> Option<i64> {
+    //Always by item id - names aren't unique, and partial matches picked up the wrong item
+    pub async fn calculate_points(&self, item_id: i64) -> Option<i64> {
         let item_record = sqlx::query!(
             "SELECT * FROM v_item_data WHERE item_id = ? ORDER BY item_id",
             item_id
@@ -273,42 +436,75 @@ impl CollectionLogManager<> {
         .await
         .ok()?;
         
-        Some(Self::points(item_record.percentage, item_record.whitelist, item_record.clamp).await?)
+        let curve = ClogCurve::load(&self.db).await.ok()?;
+        Self::points(&curve, &item_record.percentage, item_record.point_cap)
     }
 
-    pub async fn points(percentage: String, whitelist: i64, clamp: i32) -> Option<i64> {
+    pub fn points(curve: &ClogCurve, percentage: &str, point_cap: Option<i64>) -> Option<i64> {
         let completion_rate = percentage.parse::<f64>().ok()?;
-        // Multi-tiered point calculation
-        let points = if completion_rate <= 5.0 {
-            // Tier 3: Mega-rare items (≤5%)
-            // 5% -> 500 points
-            // 3% -> 1000 points
-            // 1% -> 15000 points
-            // 0.5% -> 30000 points
-            let base = 100.0;
-            let rarity_multiplier = (1.0 / completion_rate).powf(1.5) * 30.0;
-            //Is the item in a clamped category, and not whitelisted?
-            //Only checked here because the other percentage categories are nowhere near 3k
-            if whitelist == 0 && clamp > 0 {
-                (base * rarity_multiplier).clamp(0.0, 3000.0)
-            }
-            else {
-                base * rarity_multiplier
-            }
-        } else if completion_rate <= 20.0 {
-            // Tier 2: Moderately rare items (5-20%)
-            // Linear interpolation between:
-            // 20% -> 200 points
-            // 5% -> 500 points
-            let progress = (20.0 - completion_rate) / 15.0; // 0 to 1 scale
-            200.0 + (progress * 300.0)
-        } else {
-            // Tier 1: Common items (>20%)
-            // Simple linear scaling
-            100.0 - (completion_rate * 0.5)
+        //point_cap is only set if the item is in a clamped category, and not whitelisted
+        Some(curve.points(completion_rate, point_cap))
+    }
+
+    /// Works out which item someone meant. /clog's autocomplete sends the item id; anything typed by
+    /// hand has to be an exact name (either one). A name shared by several items returns all of them.
+    pub async fn find_items(&self, input: &str) -> Vec<ClogItemMatch> {
+        let input = input.trim();
+        let results = match input.parse::<i64>() {
+            Ok(item_id) => sqlx::query_as!(
+                ClogItemMatch,
+                "SELECT item_id, item_name, categories FROM collection_log_items WHERE item_id = ?",
+                item_id
+            )
+            .fetch_all(&self.db)
+            .await,
+            Err(_) => sqlx::query_as!(
+                ClogItemMatch,
+                "SELECT item_id, item_name, categories FROM collection_log_items
+                WHERE item_name = ? COLLATE NOCASE OR preferred_name = ? COLLATE NOCASE
+                ORDER BY item_id",
+                input,
+                input
+            )
+            .fetch_all(&self.db)
+            .await,
         };
 
-        Some(points.round() as i64)
+        results.unwrap_or_else(|e| {
+            error!("Failed to look up collection log item {}: {}", input, e);
+            Vec::new()
+        })
+    }
+
+    /// Autocomplete choices for /clog as (label, item id). Items that share a name get their
+    /// categories in the label so they can be told apart.
+    pub async fn get_id_suggestions(&self, partial: &str) -> Vec<(String, i64)> {
+        let partial = partial.to_lowercase();
+
+        let query_results = sqlx::query!(
+            r#"SELECT item_id, item_name, categories,
+                      (SELECT COUNT(*) FROM collection_log_items AS same WHERE same.item_name = items.item_name) as "shared_name!: i64"
+               FROM collection_log_items AS items
+               WHERE item_name LIKE '%' || ? || '%'
+               ORDER BY item_name, item_id
+               LIMIT 25"#,
+            partial
+        )
+        .fetch_all(&self.db)
+        .await
+        .unwrap_or_default();
+
+        query_results.into_iter()
+            .map(|result| {
+                let label = if result.shared_name > 1 {
+                    format!("{} ({})", result.item_name, result.categories)
+                } else {
+                    result.item_name
+                };
+                // Discord caps choice names at 100 characters
+                (label.chars().take(100).collect(), result.item_id)
+            })
+            .collect()
     }
 
     pub async fn get_suggestions(&self, partial: &str) -> Vec<String> {
@@ -342,4 +538,67 @@ impl CollectionLogManager<> {
 
         query_suggestions
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rare_tier_grows_with_rarity() {
+        let curve = ClogCurve::default();
+        assert_eq!(curve.points(5.0, None), 268);
+        assert_eq!(curve.points(1.0, None), 3000);
+        assert_eq!(curve.points(0.5, None), 8485);
+    }
+
+    #[test]
+    fn uncommon_tier_interpolates_between_its_bounds() {
+        let curve = ClogCurve::default();
+        assert_eq!(curve.points(20.0, None), 200);
+        assert_eq!(curve.points(12.5, None), 350);
+        assert_eq!(curve.points(5.000_001, None), 500);
+    }
+
+    #[test]
+    fn common_tier_falls_off_linearly() {
+        let curve = ClogCurve::default();
+        assert_eq!(curve.points(20.000_001, None), 90);
+        assert_eq!(curve.points(50.0, None), 75);
+        assert_eq!(curve.points(100.0, None), 50);
+    }
+
+    #[test]
+    fn caps_apply_to_every_tier() {
+        let curve = ClogCurve::default();
+        assert_eq!(curve.points(1.0, Some(1000)), 1000);
+        assert_eq!(curve.points(12.5, Some(300)), 300);
+        assert_eq!(curve.points(50.0, Some(1000)), 75);
+        assert_eq!(curve.points(50.0, Some(0)), 0);
+    }
+
+    #[test]
+    fn default_curve_is_valid() {
+        assert_eq!(ClogCurve::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_broken_curves() {
+        let broken = [
+            ClogCurve { rare_rate: 0.0, ..Default::default() },
+            ClogCurve { uncommon_rate: 5.0, ..Default::default() },
+            ClogCurve { rare_multiplier: f64::NAN, ..Default::default() },
+            ClogCurve { common_base: f64::INFINITY, ..Default::default() },
+            ClogCurve { rare_base: -1.0, ..Default::default() },
+            ClogCurve { rare_multiplier: -30.0, ..Default::default() },
+            ClogCurve { rare_exponent: -1.5, ..Default::default() },
+            ClogCurve { uncommon_min_points: -1.0, ..Default::default() },
+            ClogCurve { uncommon_max_points: 100.0, ..Default::default() },
+            ClogCurve { common_slope: -0.5, ..Default::default() },
+            ClogCurve { common_base: 40.0, ..Default::default() },
+        ];
+        for curve in broken {
+            assert!(curve.validate().is_err(), "{:?} should be rejected", curve);
+        }
+    }
+}
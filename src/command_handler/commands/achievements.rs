@@ -0,0 +1,107 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::format_number;
+use crate::logger;
+
+/// The achievements Dink can award points for, as (key in achievement_points, display name)
+pub const ACHIEVEMENTS: [(&str, &str); 12] = [
+    ("LEVEL_99", "Level 99 (each skill)"),
+    ("QUEST_CAPE", "Quest cape"),
+    ("CA_EASY", "Easy combat achievements"),
+    ("CA_MEDIUM", "Medium combat achievements"),
+    ("CA_HARD", "Hard combat achievements"),
+    ("CA_ELITE", "Elite combat achievements"),
+    ("CA_MASTER", "Master combat achievements"),
+    ("CA_GRANDMASTER", "Grandmaster combat achievements"),
+    ("DIARY_EASY", "Easy diary (each area)"),
+    ("DIARY_MEDIUM", "Medium diary (each area)"),
+    ("DIARY_HARD", "Hard diary (each area)"),
+    ("DIARY_ELITE", "Elite diary (each area)"),
+];
+
+fn display_name(achievement: &str) -> &str {
+    ACHIEVEMENTS.iter()
+        .find(|(key, _)| *key == achievement)
+        .map(|(_, name)| *name)
+        .unwrap_or(achievement)
+}
+
+pub async fn handle_achievement_points(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let achievement = options
+        .iter()
+        .find(|opt| opt.name == "achievement")
+        .and_then(|opt| opt.value.as_str());
+    let points = options
+        .iter()
+        .find(|opt| opt.name == "points")
+        .and_then(|opt| opt.value.as_i64());
+
+    let (Some(achievement), Some(points)) = (achievement, points) else {
+        return list_achievement_points(command, ctx, db).await;
+    };
+
+    sqlx::query!(
+        "INSERT INTO achievement_points (achievement, points) VALUES (?, ?)
+         ON CONFLICT(achievement) DO UPDATE SET points = excluded.points",
+        achievement,
+        points
+    )
+    .execute(db)
+    .await?;
+
+    let details = format!("{} is now worth {} points", display_name(achievement), format_number(points));
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "SET ACHIEVEMENT POINTS", &details).await?;
+
+    // Only applies from here on - anything already credited keeps what it was worth at the time
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(format!("{}. Achievements already credited keep their old value.", details))
+        ))
+        .await?;
+    Ok(())
+}
+
+async fn list_achievement_points(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let rows = sqlx::query!(r#"SELECT achievement as "achievement!", points FROM achievement_points"#)
+        .fetch_all(db)
+        .await?;
+
+    let list = ACHIEVEMENTS.iter()
+        .map(|(key, name)| {
+            let points = rows.iter().find(|row| row.achievement == *key).map_or(0, |row| row.points);
+            format!("• **{}**: {} points", name, format_number(points))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title("Achievement Points")
+        .color(0xffd700)
+        .description(list)
+        .footer(CreateEmbedFooter::new("Set one with /achievement_points achievement:<name> points:<number>"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{CollectionLogManagerKey, PriceManagerKey, format_gp, format_number, format_points};
+use crate::rank_manager;
+use crate::scoring;
+use crate::logger;
+use super::approval::{Submission, SubmissionKind};
+use super::clog::record_clog;
+use super::clog_remove::remove_clog;
+use super::drop::record_drop;
+use super::drop_remove::remove_drop;
+use super::undo::undo_hint;
+
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
+    Ok(())
+}
+
+// The user a mod is acting on behalf of: their discord id and the name rank announcements use
+fn target_user(command: &CommandInteraction) -> Result<(String, String)> {
+    let user_id = command.data.options
+        .iter()
+        .find(|opt| opt.name == "user")
+        .and_then(|opt| opt.value.as_user_id())
+        .ok_or_else(|| anyhow::anyhow!("User not provided"))?;
+    let resolved = &command.data.resolved;
+    let user_name = resolved.members.get(&user_id)
+        .and_then(|member| member.nick.clone())
+        .or_else(|| resolved.users.get(&user_id).map(|user| user.display_name().to_string()))
+        .unwrap_or_else(|| user_id.to_string());
+    Ok((user_id.to_string(), user_name))
+}
+
+fn reason_option(command: &CommandInteraction) -> Option<&str> {
+    command.data.options
+        .iter()
+        .find(|opt| opt.name == "reason")
+        .and_then(|opt| opt.value.as_str())
+}
+
+fn id_option(command: &CommandInteraction) -> Result<i64> {
+    command.data.options
+        .iter()
+        .find(|opt| opt.name == "id")
+        .and_then(|opt| opt.value.as_i64())
+        .ok_or_else(|| anyhow::anyhow!("ID not provided"))
+}
+
+// Where the user ended up, for the reply to the mod - rank announcements go out as usual
+fn points_summary(discord_id: &str, points_update: &rank_manager::PointsUpdate) -> String {
+    let ranks = if points_update.crossed_ranks.is_empty() {
+        String::new()
+    } else {
+        format!(" Ranks {}: {}.",
+            if points_update.new_points > points_update.old_points {"gained"} else {"lost"},
+            points_update.crossed_ranks.join(", "))
+    };
+    format!("<@{}> now has {}.{}", discord_id, format_points(points_update.new_points), ranks)
+}
+
+/// Records a drop for someone else. It's a mod vouching for it, so it skips the approval queue
+/// and doesn't need a screenshot.
+pub async fn handle_admin_drop_add(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (discord_id, user_name) = target_user(command)?;
+    let options = &command.data.options;
+    let typed_name = options
+        .iter()
+        .find(|opt| opt.name == "item")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Item name not provided"))?;
+    let quantity = options
+        .iter()
+        .find(|opt| opt.name == "quantity")
+        .and_then(|opt| opt.value.as_i64())
+        .unwrap_or(1);
+
+    let priced = {
+        let data = ctx.data.read().await;
+        let price_manager = data.get::<PriceManagerKey>()
+            .ok_or_else(|| anyhow::anyhow!("Price manager not found"))?;
+        match price_manager.resolve_item_name(typed_name).await {
+            Some(item_name) => match price_manager.get_item_id(&item_name).await {
+                Some(item_id) => price_manager.get_drop_price(item_id).await.map(|price| (item_name, item_id, price)),
+                None => None,
+            },
+            None => None,
+        }
+    };
+    let Some((item_name, item_id, price)) = priced else {
+        return respond(command, ctx, format!("Item '{}' not found in price database.", typed_name)).await;
+    };
+
+    let total_value = price.price_each * quantity;
+    let submission = Submission {
+        kind: SubmissionKind::Drop,
+        discord_id,
+        user_name,
+        points: scoring::drop_points(db, &item_name, total_value).await?,
+        item_name,
+        item_id: Some(item_id),
+        quantity,
+        value: total_value,
+        price_snapshot_id: price.snapshot_id,
+        evidence_url: None,
+    };
+    let (drop_id, points_update) = record_drop(ctx, db, &submission).await?;
+
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(
+        ctx,
+        &commanding_officer_id,
+        "ADMIN ADDED DROP",
+        &format!("{} [ID: {}] for {} ({})", submission.describe(), drop_id, submission.user_name, submission.discord_id)
+    ).await?;
+
+    respond(command, ctx, format!(
+        "Drop #{} recorded for <@{}>: {} (+{} points). {}",
+        drop_id,
+        submission.discord_id,
+        submission.describe(),
+        format_number(submission.points),
+        points_summary(&submission.discord_id, &points_update)
+    )).await
+}
+
+pub async fn handle_admin_drop_remove(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (discord_id, user_name) = target_user(command)?;
+    let drop_id = id_option(command)?;
+    let commanding_officer_id = command.user.id.to_string();
+
+    let Some((drop, points_update)) = remove_drop(ctx, db, &discord_id, &user_name, drop_id, &commanding_officer_id, reason_option(command)).await? else {
+        return respond(command, ctx, format!("<@{}> has no drop with ID {}.", discord_id, drop_id)).await;
+    };
+
+    logger::log_action(
+        ctx,
+        &commanding_officer_id,
+        "ADMIN REMOVED DROP",
+        &format!("{}x {} ({}) [ID: {}] from {} ({})", drop.quantity, drop.item_name, format_gp(drop.value), drop.id, user_name, discord_id)
+    ).await?;
+
+    let summary = match points_update {
+        Some(points_update) => format!("{} points deducted. {}", format_number(drop.points), points_summary(&discord_id, &points_update)),
+        None => "No points were deducted.".to_string(),
+    };
+    respond(command, ctx, format!(
+        "Drop #{} removed from <@{}>: {}x {} ({}). {}\n{}",
+        drop.id,
+        discord_id,
+        format_number(drop.quantity),
+        drop.item_name,
+        format_gp(drop.value),
+        summary,
+        undo_hint()
+    )).await
+}
+
+/// Records a collection log entry for someone else, skipping the approval queue like
+/// /admin_drop_add
+pub async fn handle_admin_clog_add(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (discord_id, user_name) = target_user(command)?;
+    // An item id if it was picked from autocomplete, otherwise whatever was typed
+    let item_input = command.data.options
+        .iter()
+        .find(|opt| opt.name == "item")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Item name not provided"))?;
+
+    let (mut items, points) = {
+        let data = ctx.data.read().await;
+        let collection_log_manager = data.get::<CollectionLogManagerKey>()
+            .ok_or_else(|| anyhow::anyhow!("Collection log manager not found"))?;
+        let items = collection_log_manager.find_items(item_input).await;
+        let points = match items.as_slice() {
+            [item] => collection_log_manager.calculate_points(item.item_id).await,
+            _ => None,
+        };
+        (items, points)
+    };
+    if items.len() != 1 {
+        return respond(command, ctx, if items.is_empty() {
+            format!("Item '{}' not found in collection log. Pick it from the list as you type.", item_input)
+        } else {
+            format!("More than one collection log item is called {} - pick the one you mean from the list as you type.", items[0].item_name)
+        }).await;
+    }
+    let item = items.remove(0);
+    let Some(points) = points else {
+        return respond(command, ctx, format!("Item '{}' not found in collection log.", item.item_name)).await;
+    };
+
+    let existing = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM collection_log_entries WHERE discord_id = ? AND item_id = ? AND deleted_at IS NULL"#,
+        discord_id,
+        item.item_id
+    )
+    .fetch_optional(db)
+    .await?;
+    if let Some(existing) = existing {
+        return respond(command, ctx, format!("<@{}> already has {} in their collection log (ID {}).", discord_id, item.item_name, existing)).await;
+    }
+
+    let submission = Submission {
+        kind: SubmissionKind::Clog,
+        discord_id,
+        user_name,
+        item_name: item.item_name,
+        item_id: Some(item.item_id),
+        quantity: 1,
+        value: 0,
+        points,
+        price_snapshot_id: None,
+        evidence_url: None,
+    };
+    let Some((entry_id, points_update)) = record_clog(ctx, db, &submission).await? else {
+        return respond(command, ctx, format!("<@{}> already has {} in their collection log.", submission.discord_id, submission.item_name)).await;
+    };
+
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(
+        ctx,
+        &commanding_officer_id,
+        "ADMIN ADDED CLOG",
+        &format!("{} [ID: {}] for {} ({})", submission.describe(), entry_id, submission.user_name, submission.discord_id)
+    ).await?;
+
+    respond(command, ctx, format!(
+        "Collection log entry #{} recorded for <@{}>: {} (+{} points). {}",
+        entry_id,
+        submission.discord_id,
+        submission.item_name,
+        format_number(submission.points),
+        points_summary(&submission.discord_id, &points_update)
+    )).await
+}
+
+pub async fn handle_admin_clog_remove(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (discord_id, user_name) = target_user(command)?;
+    let entry_id = id_option(command)?;
+    let commanding_officer_id = command.user.id.to_string();
+
+    let Some((entry, points_update)) = remove_clog(ctx, db, &discord_id, &user_name, entry_id, &commanding_officer_id, reason_option(command)).await? else {
+        return respond(command, ctx, format!("<@{}> has no collection log entry with ID {}.", discord_id, entry_id)).await;
+    };
+
+    logger::log_action(
+        ctx,
+        &commanding_officer_id,
+        "ADMIN REMOVED CLOG",
+        &format!("{} ({} pts) [ID: {}] from {} ({})", entry.item_name, entry.points, entry.id, user_name, discord_id)
+    ).await?;
+
+    let summary = match points_update {
+        Some(points_update) => format!("{} points deducted. {}", format_number(entry.points), points_summary(&discord_id, &points_update)),
+        None => "No points were deducted.".to_string(),
+    };
+    respond(command, ctx, format!(
+        "Collection log entry #{} removed from <@{}>: {}. {}\n{}",
+        entry.id,
+        discord_id,
+        entry.item_name,
+        summary,
+        undo_hint()
+    )).await
+}
+
+/// Gives or takes points that don't belong to any drop or clog. They go in the ledger as an
+/// 'adjustment' with the mod's reason as the source, which is what /audit_points counts them by.
+pub async fn handle_admin_points_adjust(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (discord_id, user_name) = target_user(command)?;
+    let options = &command.data.options;
+    let amount = options
+        .iter()
+        .find(|opt| opt.name == "amount")
+        .and_then(|opt| opt.value.as_i64())
+        .ok_or_else(|| anyhow::anyhow!("Amount not provided"))?;
+    let reason = options
+        .iter()
+        .find(|opt| opt.name == "reason")
+        .and_then(|opt| opt.value.as_str())
+        .map(|reason| reason.trim())
+        .ok_or_else(|| anyhow::anyhow!("Reason not provided"))?;
+
+    if amount == 0 {
+        return respond(command, ctx, "An adjustment of 0 points wouldn't change anything.".to_string()).await;
+    }
+
+    let commanding_officer_id = command.user.id.to_string();
+    let points_update = rank_manager::add_points(
+        ctx,
+        &discord_id,
+        &user_name,
+        amount,
+        "adjustment",
+        Some(&format!("<@{}>: {}", commanding_officer_id, reason)),
+        db
+    ).await?;
+
+    let change = format!("{}{}", if amount.is_positive() {"+"} else {"-"}, format_number(amount.abs()));
+    logger::log_action(
+        ctx,
+        &commanding_officer_id,
+        "ADJUSTED POINTS",
+        &format!("{} points for {} ({}): {} ({} -> {})", change, user_name, discord_id, reason, points_update.old_points, points_update.new_points)
+    ).await?;
+
+    respond(command, ctx, format!(
+        "Adjusted <@{}>'s points by {} ({}). {}",
+        discord_id,
+        change,
+        reason,
+        points_summary(&discord_id, &points_update)
+    )).await
+}
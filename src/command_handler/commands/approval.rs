@@ -0,0 +1,377 @@
+use anyhow::Result;
+use serenity::all::{
+    ButtonStyle,
+    ChannelId,
+    CommandInteraction,
+    ComponentInteraction,
+    CreateActionRow,
+    CreateButton,
+    CreateEmbed,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    CreateMessage,
+    EditInteractionResponse,
+    UserId,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{format_gp, format_number};
+use crate::config::ConfigKey;
+use crate::logger;
+use super::clog::{clog_message, record_clog};
+use super::drop::{drop_message, record_drop};
+use super::permissions::is_allowed;
+
+// Approve/Deny button ids are "approval:<approve|deny>:<submission id>"
+pub const APPROVAL_PREFIX: &str = "approval:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionKind {
+    Drop,
+    Clog,
+}
+
+impl SubmissionKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SubmissionKind::Drop => "drop",
+            SubmissionKind::Clog => "clog",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "drop" => Some(SubmissionKind::Drop),
+            "clog" => Some(SubmissionKind::Clog),
+            _ => None,
+        }
+    }
+}
+
+/// A manual /drop or /clog, either credited straight away or waiting in pending_submissions
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub kind: SubmissionKind,
+    pub discord_id: String,
+    pub user_name: String,
+    pub item_name: String,
+    pub item_id: Option<i64>,
+    pub quantity: i64,
+    // The whole stack's value, same as drops.value (0 for clogs)
+    pub value: i64,
+    pub points: i64,
+    pub price_snapshot_id: Option<i64>,
+    // Link to the screenshot it was submitted with, re-uploaded to the log channel
+    pub evidence_url: Option<String>,
+}
+
+impl Submission {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            SubmissionKind::Drop => format!("{}x {} ({})", format_number(self.quantity), self.item_name, format_gp(self.value)),
+            SubmissionKind::Clog => format!("{} (collection log)", self.item_name),
+        }
+    }
+}
+
+/// Whether a submission is worth enough that a mod has to approve it first
+pub async fn needs_approval(ctx: &serenity::prelude::Context, submission: &Submission) -> bool {
+    let data = ctx.data.read().await;
+    let Some(config) = data.get::<ConfigKey>() else {
+        return false;
+    };
+    let over_value = submission.kind == SubmissionKind::Drop
+        && config.approval_value_threshold.is_some_and(|threshold| submission.value >= threshold);
+    let over_points = config.approval_points_threshold.is_some_and(|threshold| submission.points >= threshold);
+    over_value || over_points
+}
+
+/// Whether a submission is worth enough that it has to come with a screenshot
+async fn needs_evidence(ctx: &serenity::prelude::Context, submission: &Submission) -> bool {
+    let data = ctx.data.read().await;
+    let Some(config) = data.get::<ConfigKey>() else {
+        return false;
+    };
+    let over_value = submission.kind == SubmissionKind::Drop
+        && config.evidence_value_threshold.is_some_and(|threshold| submission.value >= threshold);
+    let over_points = config.evidence_points_threshold.is_some_and(|threshold| submission.points >= threshold);
+    over_value || over_points
+}
+
+/// Re-uploads the screenshot a /drop or /clog came with (if any) to the log channel and links it
+/// to the submission. If there isn't one and the submission is worth enough to need one, or the
+/// attachment isn't an image, tells the submitter and returns false - nothing should be recorded.
+/// Downloading and re-uploading can take longer than Discord waits for a response, so this
+/// defers the interaction first - everything after it has to answer with edit_response.
+pub async fn attach_evidence(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    submission: &mut Submission,
+) -> Result<bool> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()))
+        .await?;
+
+    let screenshot = command.data.options
+        .iter()
+        .find(|opt| opt.name == "screenshot")
+        .and_then(|opt| opt.value.as_attachment_id())
+        .and_then(|id| command.data.resolved.attachments.get(&id));
+
+    let problem = match screenshot {
+        Some(screenshot) if !screenshot.content_type.as_deref().is_some_and(|content_type| content_type.starts_with("image/")) => {
+            Some(format!("The screenshot for {} has to be an image.", submission.describe()))
+        }
+        Some(screenshot) => {
+            submission.evidence_url = logger::log_evidence(ctx, &submission.discord_id, &submission.describe(), screenshot).await?;
+            None
+        }
+        None if needs_evidence(ctx, submission).await => Some(format!(
+            "{} is worth enough that it needs a screenshot - run /{} again with the `screenshot` option.",
+            submission.describe(),
+            submission.kind.as_str()
+        )),
+        None => None,
+    };
+
+    match problem {
+        Some(problem) => {
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(problem))
+                .await?;
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+fn submission_embed(id: i64, submission: &Submission, note: Option<&str>) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(format!("Pending {} #{}", if submission.kind == SubmissionKind::Drop { "Drop" } else { "Clog" }, id))
+        .color(0xffa500)
+        .field("Player", format!("<@{}> ({})", submission.discord_id, submission.user_name), false)
+        .field("Item", submission.describe(), false)
+        .field("Points", format_number(submission.points), true)
+        .field("Screenshot", submission.evidence_url.as_deref().map_or("None".to_string(), |url| format!("[View]({})", url)), true);
+    if let Some(note) = note {
+        embed = embed.field("Note", note, false);
+    }
+    embed
+}
+
+/// Puts a submission in the queue, posts it to the mod channel with Approve/Deny buttons and
+/// tells the submitter it's waiting (answering the response attach_evidence deferred). Nothing
+/// is credited until it's approved.
+pub async fn queue_submission(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    submission: &Submission,
+    note: Option<String>,
+) -> Result<()> {
+    let kind = submission.kind.as_str();
+    let channel_id = command.channel_id.to_string();
+    let id = sqlx::query!(
+        "INSERT INTO pending_submissions (kind, discord_id, user_name, item_name, item_id, quantity, value, points, price_snapshot_id, note, channel_id, evidence_url)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        kind,
+        submission.discord_id,
+        submission.user_name,
+        submission.item_name,
+        submission.item_id,
+        submission.quantity,
+        submission.value,
+        submission.points,
+        submission.price_snapshot_id,
+        note,
+        channel_id,
+        submission.evidence_url
+    )
+    .execute(db)
+    .await?
+    .last_insert_rowid();
+
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}approve:{}", APPROVAL_PREFIX, id))
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{}deny:{}", APPROVAL_PREFIX, id))
+            .label("Deny")
+            .style(ButtonStyle::Danger),
+    ]);
+    let mod_channel_id = {
+        let data = ctx.data.read().await;
+        data.get::<ConfigKey>().map(|config| config.mod_channel_id)
+    };
+    if let Some(mod_channel_id) = mod_channel_id {
+        let message = mod_channel_id
+            .send_message(&ctx.http, CreateMessage::new()
+                .embed(submission_embed(id, submission, note.as_deref()))
+                .components(vec![buttons]))
+            .await?;
+        let message_id = message.id.to_string();
+        sqlx::query!("UPDATE pending_submissions SET message_id = ? WHERE id = ?", message_id, id)
+            .execute(db)
+            .await?;
+    }
+
+    logger::log_action(ctx, &submission.discord_id, "SUBMITTED FOR APPROVAL", &format!("#{}: {}", id, submission.describe())).await?;
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(format!(
+            "{} (+{} points) is worth enough that a mod needs to approve it first. You'll get a message once they've looked at it.",
+            submission.describe(),
+            format_number(submission.points)
+        )))
+        .await?;
+    Ok(())
+}
+
+async fn respond_ephemeral(component: &ComponentInteraction, ctx: &serenity::prelude::Context, content: &str) -> Result<()> {
+    component
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content).ephemeral(true)
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Approve/Deny from a queued submission's message in the mod channel
+pub async fn handle_approval_button(
+    component: &ComponentInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let Some((action, id)) = component.data.custom_id
+        .strip_prefix(APPROVAL_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+    else {
+        return Ok(());
+    };
+    let approve = action == "approve";
+    let id: i64 = id.parse()?;
+
+    // handle_interaction only checks slash commands, so check the buttons here
+    if !is_allowed(ctx, db, component.member.as_ref(), "approval").await? {
+        return respond_ephemeral(component, ctx, "You're not allowed to approve or deny submissions.").await;
+    }
+
+    // Mods submit drops too - someone else has to look at those
+    let reviewer_id = component.user.id.to_string();
+    let submitter_id = sqlx::query_scalar!("SELECT discord_id FROM pending_submissions WHERE id = ?", id)
+        .fetch_optional(db)
+        .await?;
+    if submitter_id.as_deref() == Some(reviewer_id.as_str()) {
+        return respond_ephemeral(component, ctx, "You can't review your own submission - another mod has to.").await;
+    }
+
+    // Claim it first so two mods clicking at once can't both act on it
+    let status = if approve { "approved" } else { "denied" };
+    let claimed = sqlx::query!(
+        "UPDATE pending_submissions SET status = ?, reviewed_by = ?, reviewed_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND status = 'pending'",
+        status,
+        reviewer_id,
+        id
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+    if claimed == 0 {
+        return respond_ephemeral(component, ctx, "Someone's already dealt with this one.").await;
+    }
+
+    let row = sqlx::query!(
+        "SELECT kind, discord_id, user_name, item_name, item_id, quantity, value, points, price_snapshot_id, note, channel_id, evidence_url
+         FROM pending_submissions WHERE id = ?",
+        id
+    )
+    .fetch_one(db)
+    .await?;
+    let submission = Submission {
+        kind: SubmissionKind::parse(&row.kind).ok_or_else(|| anyhow::anyhow!("Unknown submission kind: {}", row.kind))?,
+        discord_id: row.discord_id,
+        user_name: row.user_name,
+        item_name: row.item_name,
+        item_id: row.item_id,
+        quantity: row.quantity,
+        value: row.value,
+        points: row.points,
+        price_snapshot_id: row.price_snapshot_id,
+        evidence_url: row.evidence_url,
+    };
+
+    // Crediting can take longer than Discord waits for an answer (rank roles, announcements)
+    component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+
+    let (credited, outcome, notification) = if approve {
+        let credited = match submission.kind {
+            SubmissionKind::Drop => record_drop(ctx, db, &submission).await
+                .map(|(_, points_update)| Some(drop_message(&submission, &points_update))),
+            SubmissionKind::Clog => record_clog(ctx, db, &submission).await
+                .map(|recorded| recorded.map(|(_, points_update)| clog_message(&submission, &points_update))),
+        };
+        match credited {
+            Ok(Some(message)) => (true, format!("✅ Approved by <@{}>", reviewer_id), format!("✅ A mod approved your submission! {}", message)),
+            // They got the same clog in through Dink while this was waiting
+            Ok(None) => {
+                sqlx::query!("UPDATE pending_submissions SET status = 'denied' WHERE id = ?", id)
+                    .execute(db)
+                    .await?;
+                (
+                    false,
+                    format!("❌ Not credited - {} was already in their collection log (checked by <@{}>)", submission.item_name, reviewer_id),
+                    format!("Your {} wasn't credited - it's already in your collection log.", submission.item_name),
+                )
+            }
+            Err(e) => {
+                // Put it back in the queue so it can be tried again
+                sqlx::query!(
+                    "UPDATE pending_submissions SET status = 'pending', reviewed_by = NULL, reviewed_at = NULL WHERE id = ?",
+                    id
+                )
+                .execute(db)
+                .await?;
+                return Err(e);
+            }
+        }
+    } else {
+        (
+            false,
+            format!("❌ Denied by <@{}>", reviewer_id),
+            format!("❌ A mod denied your submission of {}, so no points were awarded.", submission.describe()),
+        )
+    };
+
+    let embed = submission_embed(id, &submission, row.note.as_deref())
+        .color(if credited { 0x00ff00 } else { 0xff0000 })
+        .field("Outcome", &outcome, false);
+    component
+        .edit_response(&ctx.http, EditInteractionResponse::new().embed(embed).components(vec![]))
+        .await?;
+
+    logger::log_action(
+        ctx,
+        &reviewer_id,
+        if approve { "APPROVED SUBMISSION" } else { "DENIED SUBMISSION" },
+        &format!("#{}: {} for {} ({})", id, submission.describe(), submission.user_name, submission.discord_id)
+    ).await?;
+
+    notify_submitter(ctx, &submission.discord_id, &row.channel_id, &notification).await;
+    Ok(())
+}
+
+// DMs first; if they don't accept DMs, a mention in the channel they submitted from
+async fn notify_submitter(ctx: &serenity::prelude::Context, discord_id: &str, channel_id: &str, message: &str) {
+    let Ok(user_id) = discord_id.parse::<u64>().map(UserId::new) else {
+        return;
+    };
+    if user_id.direct_message(ctx, CreateMessage::new().content(message)).await.is_ok() {
+        return;
+    }
+    let Ok(channel_id) = channel_id.parse::<u64>().map(ChannelId::new) else {
+        return;
+    };
+    if let Err(e) = channel_id.say(&ctx.http, format!("<@{}> {}", discord_id, message)).await {
+        tracing::error!("Failed to tell {} about their submission: {:?}", discord_id, e);
+    }
+}
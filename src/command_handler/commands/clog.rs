@@ -3,11 +3,13 @@ use serenity::all::{
     CommandInteraction,
     CreateInteractionResponse,
     CreateInteractionResponseMessage,
+    EditInteractionResponse,
 };
 use sqlx::SqlitePool;
 use crate::command_handler::{CollectionLogManagerKey, format_points, format_number};
 use crate::rank_manager;
 use crate::logger;
+use super::approval::{self, Submission, SubmissionKind};
 
 pub async fn handle_clog(
     command: &CommandInteraction,
@@ -16,7 +18,8 @@ pub async fn handle_clog(
 ) -> Result<()> {
     let options = &command.data.options;
     
-    let item_name = options
+    // An item id if it was picked from autocomplete, otherwise whatever was typed
+    let item_input = options
         .iter()
         .find(|opt| opt.name == "item")
         .and_then(|opt| opt.value.as_str())
@@ -24,12 +27,38 @@ pub async fn handle_clog(
 
     let discord_id = command.user.id.to_string();
 
+    // Get collection log manager from context data
+    let data = ctx.data.read().await;
+    let collection_log_manager = data.get::<CollectionLogManagerKey>()
+        .ok_or_else(|| anyhow::anyhow!("Collection log manager not found"))?;
+
+    let mut items = collection_log_manager.find_items(item_input).await;
+    if items.len() != 1 {
+        let content = if items.is_empty() {
+            format!("Item '{}' not found in collection log. Pick it from the list as you type.", item_input)
+        } else {
+            format!(
+                "More than one collection log item is called {} - pick the one you mean from the list as you type:\n{}",
+                items[0].item_name,
+                items.iter().map(|item| format!("• {} ({})", item.item_name, item.categories)).collect::<Vec<_>>().join("\n")
+            )
+        };
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(content)
+            ))
+            .await?;
+        return Ok(());
+    }
+    let item = items.remove(0);
+    let item_name = item.item_name.as_str();
+
     // Check if user already has this collection log entry
     if let Ok(Some(existing_entry)) = sqlx::query!(
         "SELECT timestamp FROM collection_log_entries 
-         WHERE discord_id = ? AND item_name = ?",
+         WHERE discord_id = ? AND item_id = ? AND deleted_at IS NULL",
         discord_id,
-        item_name
+        item.item_id
     )
     .fetch_optional(db)
     .await
@@ -48,109 +77,54 @@ pub async fn handle_clog(
         return Ok(());
     }
 
-    // Get collection log manager from context data
-    let data = ctx.data.read().await;
-    let collection_log_manager = data.get::<CollectionLogManagerKey>()
-        .ok_or_else(|| anyhow::anyhow!("Collection log manager not found"))?;
+    // Already waiting on a mod
+    let pending = sqlx::query_scalar!(
+        "SELECT id FROM pending_submissions WHERE kind = 'clog' AND discord_id = ? AND item_id = ? AND status = 'pending'",
+        discord_id,
+        item.item_id
+    )
+    .fetch_optional(db)
+    .await?;
+    if pending.is_some() {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("Your {} is already waiting for a mod to approve it.", item_name))
+            ))
+            .await?;
+        return Ok(());
+    }
 
     // Calculate collection log points
-    if let Some(points) = collection_log_manager.calculate_points(item_name).await {
-        // Insert or update user
-        sqlx::query!(
-            "INSERT INTO users (discord_id, points, total_drops) 
-             VALUES (?, 0, 0)
-             ON CONFLICT(discord_id) DO NOTHING",
-            discord_id
-        )
-        .execute(db)
-        .await?;
-
-        let item_record = sqlx::query!("SELECT item_id, item_name FROM collection_log_items WHERE item_name = ? GROUP BY item_name ORDER BY percentage", item_name)
-        .fetch_one(db)
-        .await?;
-
-        // Record the collection log entry
-        sqlx::query!(
-            "INSERT INTO collection_log_entries (discord_id, item_name, points, item_id) VALUES (?, ?, ?, ?)",
-            discord_id,
-            item_name,
+    if let Some(points) = collection_log_manager.calculate_points(item.item_id).await {
+        let mut submission = Submission {
+            kind: SubmissionKind::Clog,
+            discord_id: discord_id.clone(),
+            user_name: command.member.as_ref()
+                .map(|m| m.display_name().to_string())
+                .unwrap_or_else(|| command.user.name.clone()),
+            item_name: item_name.to_string(),
+            item_id: Some(item.item_id),
+            quantity: 1,
+            value: 0,
             points,
-            item_record.item_id,
-        )
-        .execute(db)
-        .await?;
-        
-        // Log the collection log entry
-        logger::log_action(
-            ctx,
-            &discord_id,
-            "ADDED CLOG",
-            &format!("{} ({} pts)", item_name, format_number(points))
-        ).await?;
-
-        // Add points and check for rank up
-        let points_update = rank_manager::add_points(
-            ctx,
-            &discord_id,
-            &command.member.as_ref()
-                .and_then(|m| Some(m.display_name()))
-                .unwrap_or(&command.user.name),
-            points,
-            db
-        ).await?;
-
-        // Format response message
-        let message_content = if !points_update.crossed_ranks.is_empty() {
-            // User ranked up!
-            let rank_text = if points_update.crossed_ranks.len() == 1 {
-                format!("the {} rank", points_update.crossed_ranks[0])
-            } else {
-                let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
-                match ranks.len() {
-                    2 => format!("the {} and {} ranks", ranks[0], ranks[1]),
-                    _ => {
-                        let (last, rest) = ranks.split_last().unwrap();
-                        format!("the {}, and {} ranks", rest.join(", "), last)
-                    }
-                }
-            };
-            
-            let next_rank_info = if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
-                format!(" Next rank at {} points for {}!", format_number(*next_rank_points), next_rank_name)
-            } else {
-                "".to_string()
-            };
-            
-            format!(
-                "🎆 🎇 **RANK UP!** 🎇 🎆\nCollection log entry recorded: {} (+{} points)! You now have {} and achieved {}!{}",
-                item_name,
-                format_number(points),
-                format_points(points_update.new_points),
-                rank_text,
-                next_rank_info
-            )
-        } else if let Some((next_rank_points, next_rank_name)) = points_update.next_rank {
-            format!(
-                "Collection log entry recorded: {} (+{} points)! You now have {}. Next rank at {} points for {}!",
-                item_name,
-                format_number(points),
-                format_points(points_update.new_points),
-                format_number(next_rank_points),
-                next_rank_name
-            )
-        } else {
-            format!(
-                "Collection log entry recorded: {} (+{} points)! You now have {}!",
-                item_name,
-                format_number(points),
-                format_points(points_update.new_points)
-            )
+            price_snapshot_id: None,
+            evidence_url: None,
         };
+        if !approval::attach_evidence(command, ctx, &mut submission).await? {
+            return Ok(());
+        }
+
+        if approval::needs_approval(ctx, &submission).await {
+            return approval::queue_submission(command, ctx, db, &submission, None).await;
+        }
 
+        let content = match record_clog(ctx, db, &submission).await? {
+            Some((_, points_update)) => clog_message(&submission, &points_update),
+            None => format!("You've already logged {} in your collection log!", item_name),
+        };
         command
-            .create_response(&ctx.http, CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new().content(message_content)
-            ))
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
             .await?;
     } else {
         command
@@ -162,4 +136,130 @@ pub async fn handle_clog(
     }
 
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// Records a collection log entry and credits its points - straight from /clog, or once a mod
+/// approves it. None if they already have the item logged (it may have come in through Dink
+/// since it was checked), in which case nothing is credited.
+pub async fn record_clog(
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    submission: &Submission,
+) -> Result<Option<(i64, rank_manager::PointsUpdate)>> {
+    // The entry and its points go in together, so there's never one without the other
+    let mut tx = db.begin().await?;
+
+    // Insert or update user
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) 
+         VALUES (?, 0, 0)
+         ON CONFLICT(discord_id) DO NOTHING",
+        submission.discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Record the collection log entry - the unique index on live entries per user and item turns
+    // a second one into a no-op
+    let inserted = sqlx::query!(
+        "INSERT INTO collection_log_entries (discord_id, item_name, points, item_id, evidence_url) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(discord_id, item_id) WHERE deleted_at IS NULL DO NOTHING",
+        submission.discord_id,
+        submission.item_name,
+        submission.points,
+        submission.item_id,
+        submission.evidence_url,
+    )
+    .execute(&mut *tx)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(None);
+    }
+    let entry_id = inserted.last_insert_rowid();
+
+    let (old_points, new_points) = rank_manager::record_points(
+        &mut tx,
+        &submission.discord_id,
+        submission.points,
+        "clog",
+        Some(&format!("clog #{}", entry_id))
+    ).await?;
+    tx.commit().await?;
+    
+    // Log the collection log entry
+    logger::log_action(
+        ctx,
+        &submission.discord_id,
+        "ADDED CLOG",
+        &format!(
+            "{} ({} pts){}",
+            submission.item_name,
+            format_number(submission.points),
+            submission.evidence_url.as_deref().map(|url| format!(" [screenshot]({})", url)).unwrap_or_default()
+        )
+    ).await?;
+
+    // Check for rank up
+    let points_update = rank_manager::finish_points_update(
+        ctx,
+        &submission.discord_id,
+        &submission.user_name,
+        old_points,
+        new_points,
+        db
+    ).await?;
+
+    Ok(Some((entry_id, points_update)))
+}
+
+/// What the submitter is told once a clog has been credited
+pub fn clog_message(submission: &Submission, points_update: &rank_manager::PointsUpdate) -> String {
+    let item_name = &submission.item_name;
+    let points = submission.points;
+    if !points_update.crossed_ranks.is_empty() {
+        // User ranked up!
+        let rank_text = if points_update.crossed_ranks.len() == 1 {
+            format!("the {} rank", points_update.crossed_ranks[0])
+        } else {
+            let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
+            match ranks.len() {
+                2 => format!("the {} and {} ranks", ranks[0], ranks[1]),
+                _ => {
+                    let (last, rest) = ranks.split_last().unwrap();
+                    format!("the {}, and {} ranks", rest.join(", "), last)
+                }
+            }
+        };
+        
+        let next_rank_info = if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
+            format!(" Next rank at {} points for {}!", format_number(*next_rank_points), next_rank_name)
+        } else {
+            "".to_string()
+        };
+        
+        format!(
+            "🎆 🎇 **RANK UP!** 🎇 🎆\nCollection log entry recorded: {} (+{} points)! You now have {} and achieved {}!{}",
+            item_name,
+            format_number(points),
+            format_points(points_update.new_points),
+            rank_text,
+            next_rank_info
+        )
+    } else if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
+        format!(
+            "Collection log entry recorded: {} (+{} points)! You now have {}. Next rank at {} points for {}!",
+            item_name,
+            format_number(points),
+            format_points(points_update.new_points),
+            format_number(*next_rank_points),
+            next_rank_name
+        )
+    } else {
+        format!(
+            "Collection log entry recorded: {} (+{} points)! You now have {}!",
+            item_name,
+            format_number(points),
+            format_points(points_update.new_points)
+        )
+    }
+}
\ No newline at end of file
@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use sqlx::SqlitePool;
+use crate::collection_log::ClogCurve;
+use crate::command_handler::format_number;
+use crate::rank_manager;
+use crate::logger;
+use crate::runescape_tracker::RunescapeTrackerKey;
+use super::moderation::notify_recalculated_players;
+
+// Completion rates (%) to show sample items for, picked to land a few in each tier of the default curve
+const SAMPLE_RATES: [f64; 8] = [0.5, 1.0, 3.0, 5.0, 10.0, 20.0, 40.0, 80.0];
+
+struct ScoredEntry {
+    id: i64,
+    discord_id: String,
+    item_name: String,
+    points: i64,
+    new_points: i64,
+}
+
+fn describe(curve: &ClogCurve) -> String {
+    format!(
+        "• **Rare** (≤ {}%): {} × (1 / rate)^{} × {}\n\
+         • **Uncommon** (≤ {}%): {} points at {}%, rising to {} at {}%\n\
+         • **Common**: {} - rate × {}",
+        curve.rare_rate, curve.rare_base, curve.rare_exponent, curve.rare_multiplier,
+        curve.uncommon_rate, curve.uncommon_min_points, curve.uncommon_rate, curve.uncommon_max_points, curve.rare_rate,
+        curve.common_base, curve.common_slope
+    )
+}
+
+fn signed(delta: i64) -> String {
+    format!("{}{}", if delta.is_positive() {"+"} else {""}, format_number(delta))
+}
+
+/// Every clog entry on record, scored with `curve`
+async fn score_entries(db: &SqlitePool, curve: &ClogCurve) -> Result<Vec<ScoredEntry>> {
+    let rows = sqlx::query!(
+        r#"SELECT e.id as "id!", e.discord_id, e.item_name, e.points,
+                  i.percentage as "percentage!: String", i.point_cap as "point_cap: i64"
+           FROM collection_log_entries e
+           JOIN v_item_data i ON i.item_id = e.item_id
+           WHERE e.deleted_at IS NULL"#
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter()
+        .filter_map(|row| {
+            let rate = row.percentage.parse::<f64>().ok()?;
+            Some(ScoredEntry {
+                id: row.id,
+                discord_id: row.discord_id,
+                item_name: row.item_name,
+                points: row.points,
+                new_points: curve.points(rate, row.point_cap),
+            })
+        })
+        .collect())
+}
+
+/// The item closest to each of SAMPLE_RATES, old points vs new
+async fn sample_items(db: &SqlitePool, old: &ClogCurve, new: &ClogCurve) -> Result<String> {
+    let mut lines = Vec::new();
+    let mut seen = Vec::new();
+    for rate in SAMPLE_RATES {
+        let item = sqlx::query!(
+            r#"SELECT item_id as "item_id!", item_name, percentage as "percentage!: String", point_cap as "point_cap: i64"
+               FROM v_item_data
+               ORDER BY ABS(CAST(percentage AS REAL) - ?) ASC
+               LIMIT 1"#,
+            rate
+        )
+        .fetch_optional(db)
+        .await?;
+
+        let Some(item) = item else { continue };
+        let Ok(item_rate) = item.percentage.parse::<f64>() else { continue };
+        if seen.contains(&item.item_id) {
+            continue;
+        }
+        seen.push(item.item_id);
+
+        let old_points = old.points(item_rate, item.point_cap);
+        let new_points = new.points(item_rate, item.point_cap);
+        let points = if old == new {
+            format_number(new_points)
+        } else {
+            format!("{} → {} ({})", format_number(old_points), format_number(new_points), signed(new_points - old_points))
+        };
+        lines.push(format!("• **{}** ({}%{}): {}", item.item_name, item_rate, item.point_cap.map(|cap| format!(", capped at {}", format_number(cap))).unwrap_or_default(), points));
+    }
+
+    Ok(if lines.is_empty() { "No collection log items loaded yet".to_string() } else { lines.join("\n") })
+}
+
+// Options that aren't given keep their current value
+fn apply_options(command: &CommandInteraction, curve: &ClogCurve) -> (ClogCurve, bool) {
+    let mut new_curve = *curve;
+    let mut changed = false;
+    for opt in &command.data.options {
+        let Some(value) = opt.value.as_f64() else { continue };
+        let field = match opt.name.as_str() {
+            "rare_rate" => &mut new_curve.rare_rate,
+            "rare_base" => &mut new_curve.rare_base,
+            "rare_multiplier" => &mut new_curve.rare_multiplier,
+            "rare_exponent" => &mut new_curve.rare_exponent,
+            "uncommon_rate" => &mut new_curve.uncommon_rate,
+            "uncommon_min_points" => &mut new_curve.uncommon_min_points,
+            "uncommon_max_points" => &mut new_curve.uncommon_max_points,
+            "common_base" => &mut new_curve.common_base,
+            "common_slope" => &mut new_curve.common_slope,
+            _ => continue,
+        };
+        *field = value;
+        changed = true;
+    }
+    (new_curve, changed)
+}
+
+pub async fn handle_clog_curve(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let apply = command.data.options
+        .iter()
+        .find(|opt| opt.name == "apply")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    let curve = ClogCurve::load(db).await?;
+    let (new_curve, changed) = apply_options(command, &curve);
+
+    if let Err(reason) = new_curve.validate() {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(format!("That curve doesn't work: {}.", reason))
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    if apply && changed {
+        return apply_curve(command, ctx, db, &curve, &new_curve).await;
+    }
+
+    let mut embed = if changed {
+        // Preview only - nothing is saved until it's run again with apply:True
+        let entries = score_entries(db, &new_curve).await?;
+        let changed_entries: Vec<_> = entries.iter().filter(|entry| entry.new_points != entry.points).collect();
+        let total_delta: i64 = changed_entries.iter().map(|entry| entry.new_points - entry.points).sum();
+        let players = changed_entries.iter().map(|entry| entry.discord_id.as_str()).collect::<HashSet<_>>().len();
+
+        CreateEmbed::new()
+            .title("Clog Curve Preview")
+            .color(0xffa500)
+            .field("New Curve", describe(&new_curve), false)
+            .field("Sample Items (current → new)", sample_items(db, &curve, &new_curve).await?, false)
+            .field(
+                "Existing Entries",
+                format!("{} clogs would change for {} players, {} points in total", format_number(changed_entries.len() as i64), players, signed(total_delta)),
+                false
+            )
+            .footer(CreateEmbedFooter::new("Nothing has been saved - run it again with apply:True to switch to this curve"))
+    } else {
+        CreateEmbed::new()
+            .title("Clog Curve")
+            .color(0xffd700)
+            .field("Current Curve", describe(&curve), false)
+            .field("Sample Items", sample_items(db, &curve, &curve).await?, false)
+            .footer(CreateEmbedFooter::new("Pass any of the curve options to preview a change"))
+    };
+    if apply && !changed {
+        embed = embed.description("Nothing to apply - pass the curve options you want to change along with apply:True.");
+    }
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
+
+// Saves the curve and rescores every clog on record with it. Unlike /recalculate, points go down
+// as well as up - the whole point of changing the curve is to change what clogs are worth.
+async fn apply_curve(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    old_curve: &ClogCurve,
+    new_curve: &ClogCurve,
+) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content("Applying new clog curve...")
+        ))
+        .await?;
+
+    let entries = score_entries(db, new_curve).await?;
+    let mut affected_player_deltas: HashMap<String, i64> = HashMap::new();
+    let mut affected_items: HashMap<String, (i64, i64)> = HashMap::new(); // item -> (clogs affected, point delta)
+
+    let mut tx = db.begin().await?;
+    new_curve.save(&mut *tx).await?;
+    let mut running_count = 0;
+    // One ledger row per changed entry, same as /recalculate
+    for entry in entries.iter().filter(|entry| entry.new_points != entry.points) {
+        let point_delta = entry.new_points - entry.points;
+        sqlx::query!("UPDATE collection_log_entries SET points = ? WHERE id = ?", entry.new_points, entry.id)
+            .execute(&mut *tx)
+            .await?;
+
+        rank_manager::record_points(
+            &mut tx,
+            &entry.discord_id,
+            point_delta,
+            "recalculation",
+            Some(&format!("clog #{}", entry.id)),
+        )
+        .await?;
+
+        running_count += 1;
+        *affected_player_deltas.entry(entry.discord_id.clone()).or_insert(0) += point_delta;
+        let item = affected_items.entry(entry.item_name.clone()).or_insert((0, 0));
+        item.0 += 1;
+        item.1 += point_delta;
+    }
+    tx.commit().await?;
+    tracing::info!("Clog curve changed, {} clog entries rescored", running_count);
+
+    let affected_players = {
+        let data = ctx.data.read().await;
+        let rs_manager = data.get::<RunescapeTrackerKey>().unwrap();
+        notify_recalculated_players(ctx, rs_manager, affected_player_deltas, db).await
+    };
+
+    let mut info_readout = format!(
+        "\n**Clog curve changed**\nFrom:\n{}\nTo:\n{}\n{} total clogs affected!",
+        describe(old_curve),
+        describe(new_curve),
+        running_count
+    );
+    for (item_name, (affected, point_delta)) in affected_items {
+        info_readout += format!("\n**{}**: {} clogs, **{}** points", item_name, affected, signed(point_delta)).as_str();
+    }
+    if !affected_players.is_empty() {
+        info_readout += "\n**Affected users:**";
+    }
+    for (discord_id, player) in affected_players {
+        info_readout += format!("\n**{}** ({}): **{}** points", player.name, discord_id, signed(player.change)).as_str();
+    }
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new()
+            .content(format!("Clog curve updated! {} clogs rescored.", running_count)))
+        .await?;
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "CHANGED CLOG CURVE", &info_readout).await?;
+    Ok(())
+}
@@ -7,6 +7,7 @@ use serenity::all::{
 use sqlx::SqlitePool;
 use crate::rank_manager;
 use crate::logger;
+use super::undo::undo_hint;
 
 pub async fn handle_clog_remove(
     command: &CommandInteraction,
@@ -17,11 +18,11 @@ pub async fn handle_clog_remove(
     
     // Get the user's most recent collection log entries (top 10)
     let recent_entries = sqlx::query!(
-        "SELECT id, item_name, points, timestamp 
+        r#"SELECT id as "id!", item_name, points, timestamp 
          FROM collection_log_entries 
-         WHERE discord_id = ? 
+         WHERE discord_id = ? AND deleted_at IS NULL
          ORDER BY timestamp DESC 
-         LIMIT 10",
+         LIMIT 10"#,
         discord_id
     )
     .fetch_all(db)
@@ -78,108 +79,149 @@ pub async fn handle_clog_remove(
         }
     };
     
-    // Find the collection log entry with the given ID
-    let entry_to_remove = sqlx::query!(
-        "SELECT id, item_name, points 
-         FROM collection_log_entries 
-         WHERE id = ? AND discord_id = ?",
-        entry_id,
-        discord_id
-    )
-    .fetch_optional(db)
-    .await?;
-    
-    match entry_to_remove {
-        Some(entry) => {
-            // Begin transaction
-            let mut tx = db.begin().await?;
-            
-            // Remove the collection log entry
-            sqlx::query!(
-                "DELETE FROM collection_log_entries WHERE id = ?",
-                entry.id
-            )
-            .execute(&mut *tx)
-            .await?;
-            
-            // Log the collection log entry removal
-            logger::log_action(
-                ctx,
-                &discord_id,
-                "REMOVED CLOG",
-                &format!("{} ({} pts) [ID: {}]", entry.item_name, entry.points, entry.id)
-            ).await?;
-            
-            // Commit transaction
-            tx.commit().await?;
-            
-            // Deduct points from user
-            if entry.points > 0 {
-                let points_update = rank_manager::add_points(
-                    ctx,
-                    &discord_id,
-                    &command.member.as_ref()
-                        .and_then(|m| Some(m.display_name()))
-                        .unwrap_or(&command.user.name),
-                    -entry.points, // Negative to deduct points
-                    db
-                ).await?;
-                
-                let message = if !points_update.crossed_ranks.is_empty() {
-                    // User ranked down
-                    let rank_text = if points_update.crossed_ranks.len() == 1 {
-                        format!("You have lost the {} rank.", points_update.crossed_ranks[0])
-                    } else {
-                        let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
-                        match ranks.len() {
-                            2 => format!("You have lost the {} and {} ranks.", ranks[0], ranks[1]),
-                            _ => {
-                                let (last, rest) = ranks.split_last().unwrap();
-                                format!("You have lost the {}, and {} ranks.", rest.join(", "), last)
-                            }
-                        }
-                    };
-                    
-                    format!(
-                        "Collection log entry removed: {} ({} pts). Points have been deducted from your total.\n⬇️ **RANK DOWN!** ⬇️\n{}",
-                        entry.item_name,
-                        entry.points,
-                        rank_text
-                    )
+    let user_name = command.member.as_ref()
+        .map(|m| m.display_name().to_string())
+        .unwrap_or_else(|| command.user.name.clone());
+    let reason = command.data.options
+        .iter()
+        .find(|opt| opt.name == "reason")
+        .and_then(|opt| opt.value.as_str());
+    let removed = remove_clog(ctx, db, &discord_id, &user_name, entry_id, &discord_id, reason).await?;
+    let found = removed.is_some();
+    let content = match removed {
+        Some((entry, Some(points_update))) => {
+            if !points_update.crossed_ranks.is_empty() {
+                // User ranked down
+                let rank_text = if points_update.crossed_ranks.len() == 1 {
+                    format!("You have lost the {} rank.", points_update.crossed_ranks[0])
                 } else {
-                    format!(
-                        "Collection log entry removed: {} ({} pts). Points have been deducted from your total.",
-                        entry.item_name,
-                        entry.points
-                    )
+                    let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
+                    match ranks.len() {
+                        2 => format!("You have lost the {} and {} ranks.", ranks[0], ranks[1]),
+                        _ => {
+                            let (last, rest) = ranks.split_last().unwrap();
+                            format!("You have lost the {}, and {} ranks.", rest.join(", "), last)
+                        }
+                    }
                 };
                 
-                command
-                    .create_response(&ctx.http, CreateInteractionResponse::Message(
-                        CreateInteractionResponseMessage::new().content(message)
-                    ))
-                    .await?;
+                format!(
+                    "Collection log entry removed: {} ({} pts). Points have been deducted from your total.\n⬇️ **RANK DOWN!** ⬇️\n{}",
+                    entry.item_name,
+                    entry.points,
+                    rank_text
+                )
             } else {
-                command
-                    .create_response(&ctx.http, CreateInteractionResponse::Message(
-                        CreateInteractionResponseMessage::new()
-                            .content(format!(
-                                "Collection log entry removed: {} (0 pts). No points were deducted.",
-                                entry.item_name
-                            ))
-                    ))
-                    .await?;
+                format!(
+                    "Collection log entry removed: {} ({} pts). Points have been deducted from your total.",
+                    entry.item_name,
+                    entry.points
+                )
             }
         },
-        None => {
-            command
-                .create_response(&ctx.http, CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .content(format!("No collection log entry found with ID {}.", entry_id))
-                ))
-                .await?;
-        }
-    }
+        Some((entry, None)) => format!(
+            "Collection log entry removed: {} (0 pts). No points were deducted.",
+            entry.item_name
+        ),
+        None => format!("No collection log entry found with ID {}.", entry_id),
+    };
+
+    let content = if found { format!("{}\n{}", content, undo_hint()) } else { content };
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
     
     Ok(())
+}
+
+/// A collection log entry that's just been taken off someone's record
+pub struct RemovedClog {
+    pub id: i64,
+    pub item_name: String,
+    pub points: i64,
+}
+
+/// Removes one of a user's collection log entries and takes its points back off them - from
+/// /clog_remove, or a mod's /admin_clog_remove. Like drops, the row is only marked as deleted so
+/// /undo can bring it back. None if they don't have an entry with that ID. The PointsUpdate is
+/// None if the entry wasn't worth any points.
+pub async fn remove_clog(
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    discord_id: &str,
+    user_name: &str,
+    entry_id: i64,
+    removed_by: &str,
+    reason: Option<&str>,
+) -> Result<Option<(RemovedClog, Option<rank_manager::PointsUpdate>)>> {
+    // Find the collection log entry with the given ID
+    let Some(entry) = sqlx::query_as!(
+        RemovedClog,
+        "SELECT id, item_name, points 
+         FROM collection_log_entries 
+         WHERE id = ? AND discord_id = ? AND deleted_at IS NULL",
+        entry_id,
+        discord_id
+    )
+    .fetch_optional(db)
+    .await? else {
+        return Ok(None);
+    };
+
+    // The removal and the points coming off go in together
+    let mut tx = db.begin().await?;
+
+    // Remove the collection log entry - unless it's been removed since it was looked up, so the
+    // points don't come off twice
+    let removed = sqlx::query!(
+        "UPDATE collection_log_entries SET deleted_at = CURRENT_TIMESTAMP, deleted_by = ?, delete_reason = ? WHERE id = ? AND deleted_at IS NULL",
+        removed_by,
+        reason,
+        entry.id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if removed == 0 {
+        return Ok(None);
+    }
+
+    // Deduct points from user
+    let totals = if entry.points > 0 {
+        Some(rank_manager::record_points(
+            &mut tx,
+            discord_id,
+            -entry.points, // Negative to deduct points
+            "clog_removed",
+            Some(&format!("clog #{}", entry.id))
+        ).await?)
+    } else {
+        None
+    };
+
+    // Commit transaction
+    tx.commit().await?;
+
+    // Log the collection log entry removal
+    logger::log_action(
+        ctx,
+        discord_id,
+        "REMOVED CLOG",
+        &format!(
+            "{} ({} pts) [ID: {}]{}",
+            entry.item_name,
+            entry.points,
+            entry.id,
+            reason.map(|reason| format!(" - {}", reason)).unwrap_or_default()
+        )
+    ).await?;
+
+    let Some((old_points, new_points)) = totals else {
+        return Ok(Some((entry, None)));
+    };
+    let points_update = rank_manager::finish_points_update(ctx, discord_id, user_name, old_points, new_points, db).await?;
+    Ok(Some((entry, Some(points_update))))
 } 
\ No newline at end of file
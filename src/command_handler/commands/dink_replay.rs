@@ -0,0 +1,124 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use sqlx::SqlitePool;
+use crate::dink_listener::{self, DinkHandlerKey, ReplayError};
+use crate::logger;
+
+pub async fn handle_dink_replay(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let inbox_id = options
+        .iter()
+        .find(|opt| opt.name == "id")
+        .and_then(|opt| opt.value.as_i64());
+    let force = options
+        .iter()
+        .find(|opt| opt.name == "force")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    let Some(inbox_id) = inbox_id else {
+        return list_unprocessed(command, ctx, db).await;
+    };
+
+    let handler = {
+        let data = ctx.data.read().await;
+        data.get::<DinkHandlerKey>().cloned()
+    };
+    let Some(handler) = handler else {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("The Dink listener isn't running yet, try again in a moment.")
+            ))
+            .await?;
+        return Ok(());
+    };
+
+    // Replaying posts to the RuneLite channel and may hand out points, which can take longer
+    // than Discord waits for a response
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(format!("Replaying Dink event #{}...", inbox_id))
+        ))
+        .await?;
+
+    let result = match dink_listener::replay_dink_event(handler, inbox_id, force).await {
+        Ok(status) => {
+            let commanding_officer_id = command.user.id.to_string();
+            logger::log_action(ctx, &commanding_officer_id, "REPLAYED DINK EVENT",
+                &format!("#{}{} -> {}", inbox_id, if force {" (forced)"} else {""}, status.as_str())).await?;
+            format!("Replayed Dink event #{}: {}", inbox_id, status.as_str())
+        }
+        Err(ReplayError::NotFound) => format!("There's no Dink event #{}.", inbox_id),
+        Err(e @ ReplayError::AlreadyProcessed(_)) => format!("Dink event #{} not replayed: {}. Use `force` if you're sure.", inbox_id, e),
+        Err(e @ ReplayError::AlreadyCredited(_)) => format!("Dink event #{} not replayed: {}.", inbox_id, e),
+        Err(e) => return Err(anyhow::anyhow!("Failed to replay Dink event #{}: {}", inbox_id, e)),
+    };
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(result))
+        .await?;
+    Ok(())
+}
+
+// Everything that didn't make it all the way through, newest first
+async fn list_unprocessed(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let entries = sqlx::query!(
+        r#"SELECT id as "id!", status, error, received_at,
+                  json_extract(payload, '$.type') as "notif_type: String",
+                  json_extract(payload, '$.playerName') as "player_name: String"
+           FROM dink_inbox
+           WHERE status NOT IN ('processed', 'duplicate', 'pending')
+           ORDER BY id DESC
+           LIMIT 20"#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let list = if entries.is_empty() {
+        "Nothing waiting to be replayed!".to_string()
+    } else {
+        entries.iter()
+            .map(|entry| {
+                format!(
+                    "**#{}** {} from {} ({}) - {}{}",
+                    entry.id,
+                    entry.notif_type.as_deref().unwrap_or("?"),
+                    entry.player_name.as_deref().unwrap_or("?"),
+                    entry.received_at.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+                    entry.status,
+                    entry.error.as_deref().map(|e| format!(": {}", e)).unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Unprocessed Dink Events")
+        .color(0xff9900)
+        .description(list)
+        .footer(CreateEmbedFooter::new("Replay one with /dink_replay id:<number>"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
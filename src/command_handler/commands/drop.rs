@@ -1,13 +1,20 @@
 use anyhow::Result;
+use chrono::Utc;
 use serenity::all::{
     CommandInteraction,
     CreateInteractionResponse,
     CreateInteractionResponseMessage,
+    EditInteractionResponse,
 };
 use sqlx::SqlitePool;
 use crate::command_handler::{PriceManagerKey, format_gp, format_points, format_number};
 use crate::rank_manager;
+use crate::scoring;
 use crate::logger;
+use super::approval::{self, Submission, SubmissionKind};
+
+// How old prices can get before /drop warns that they might be out of date
+const STALE_PRICES_MINUTES: i64 = 30;
 
 pub async fn handle_drop(
     command: &CommandInteraction,
@@ -16,7 +23,7 @@ pub async fn handle_drop(
 ) -> Result<()> {
     let options = &command.data.options;
     
-    let item_name = options
+    let typed_name = options
         .iter()
         .find(|opt| opt.name == "item")
         .and_then(|opt| opt.value.as_str())
@@ -33,130 +40,220 @@ pub async fn handle_drop(
     let price_manager = data.get::<PriceManagerKey>()
         .ok_or_else(|| anyhow::anyhow!("Price manager not found"))?;
 
-    // Get item price
-    if let Some(value) = price_manager.get_item_price(item_name).await {
-        let discord_id = command.user.id.to_string();
-        let total_value = value * quantity;
-        let points = total_value / 100_000; // 1 point per 100,000 gp
-
-        // Insert or update user
-        sqlx::query!(
-            "INSERT INTO users (discord_id, points, total_drops) 
-             VALUES (?, 0, 0)
-             ON CONFLICT(discord_id) DO NOTHING",
-            discord_id
-        )
-        .execute(db)
-        .await?;
-
-        // Record the drop
-        sqlx::query!(
-            "INSERT INTO drops (discord_id, item_name, value, quantity) VALUES (?, ?, ?, ?)",
-            discord_id,
-            item_name,
-            total_value,
-            quantity
-        )
-        .execute(db)
-        .await?;
-
-        // Log the drop
-        logger::log_action(
-            ctx,
-            &discord_id,
-            "ADDED DROP",
-            &format!("{}x {} ({})", quantity, item_name, format_gp(total_value))
-        ).await?;
-
-        // Update total drops
-        sqlx::query!(
-            "UPDATE users 
-             SET total_drops = total_drops + ?
-             WHERE discord_id = ?",
+    // Work out which item was meant (typos, nicknames, wrong capitalisation), then price it.
+    // Everything from here on - the drop, the log, the reply - uses the wiki's name for it.
+    let resolved_name = price_manager.resolve_item_name(typed_name).await;
+    let item_id = match &resolved_name {
+        Some(item_name) => price_manager.get_item_id(item_name).await,
+        None => None,
+    };
+    let price = match item_id {
+        Some(item_id) => price_manager.get_drop_price(item_id).await,
+        None => None,
+    };
+    if let (Some(item_name), Some(price)) = (resolved_name.as_deref(), price) {
+        let total_value = price.price_each * quantity;
+        let mut submission = Submission {
+            kind: SubmissionKind::Drop,
+            discord_id: command.user.id.to_string(),
+            user_name: command.member.as_ref()
+                .map(|m| m.display_name().to_string())
+                .unwrap_or_else(|| command.user.name.clone()),
+            item_name: item_name.to_string(),
+            item_id,
             quantity,
-            discord_id
-        )
-        .execute(db)
-        .await?;
-
-        // Add points and check for rank up
-        let points_update = rank_manager::add_points(
-            ctx,
-            &discord_id,
-            &command.member.as_ref()
-                .and_then(|m| Some(m.display_name()))
-                .unwrap_or(&command.user.name),
-            points,
-            db
-        ).await?;
-
-        // Format response message
-        let message_content = if !points_update.crossed_ranks.is_empty() {
-            // User ranked up!
-            let rank_text = if points_update.crossed_ranks.len() == 1 {
-                format!("the {} rank", points_update.crossed_ranks[0])
-            } else {
-                let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
-                match ranks.len() {
-                    2 => format!("the {} and {} ranks", ranks[0], ranks[1]),
-                    _ => {
-                        let (last, rest) = ranks.split_last().unwrap();
-                        format!("the {}, and {} ranks", rest.join(", "), last)
-                    }
-                }
-            };
-            
-            let next_rank_info = if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
-                format!(" Next rank at {} points for {}!", format_number(*next_rank_points), next_rank_name)
-            } else {
-                "".to_string()
-            };
-            
-            format!(
-                "🎆 🎇 **RANK UP!** 🎇 🎆\nDrop recorded: {}x {} ({}) (+{} points)! You now have {} and achieved {}!{}",
-                format_number(quantity),
-                item_name,
-                format_gp(total_value),
-                format_number(points),
-                format_points(points_update.new_points),
-                rank_text,
-                next_rank_info
-            )
-        } else if let Some((next_rank_points, next_rank_name)) = points_update.next_rank {
-            format!(
-                "Drop recorded: {}x {} ({}) (+{} points)! You now have {}. Next rank at {} points for {}!",
-                format_number(quantity),
-                item_name,
-                format_gp(total_value),
-                format_number(points),
-                format_points(points_update.new_points),
-                format_number(next_rank_points),
-                next_rank_name
-            )
-        } else {
-            format!(
-                "Drop recorded: {}x {} ({}) (+{} points)! You now have {}!",
-                format_number(quantity),
-                item_name,
-                format_gp(total_value),
-                format_number(points),
-                format_points(points_update.new_points)
-            )
+            value: total_value,
+            points: scoring::drop_points(db, item_name, total_value).await?,
+            price_snapshot_id: price.snapshot_id,
+            evidence_url: None,
+        };
+        if !approval::attach_evidence(command, ctx, &mut submission).await? {
+            return Ok(());
+        }
+
+        if approval::needs_approval(ctx, &submission).await {
+            let note = price.flag.as_ref().map(|flag| format!(
+                "⚠️ Market price not trusted ({}) - valued at {} each instead of {}",
+                flag.reason,
+                format_gp(price.price_each),
+                format_gp(flag.market_price)
+            ));
+            return approval::queue_submission(command, ctx, db, &submission, note).await;
+        }
+
+        let (drop_id, points_update) = record_drop(ctx, db, &submission).await?;
+        logger::flag_drop_price(ctx, drop_id, &submission.discord_id, quantity, item_name, &price).await?;
+        let message_content = drop_message(&submission, &points_update);
+
+        // Prices refresh every 10 minutes, so anything much older means we're running off the cache
+        let message_content = match price_manager.prices_updated_at().await {
+            Some(updated_at) if Utc::now() - updated_at > chrono::Duration::minutes(STALE_PRICES_MINUTES) => format!(
+                "{}\n⚠️ Prices were last updated <t:{}:R> - the wiki's price API may be down, so this value could be out of date.",
+                message_content,
+                updated_at.timestamp()
+            ),
+            _ => message_content,
         };
 
         command
-            .create_response(&ctx.http, CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new().content(message_content)
-            ))
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(message_content))
             .await?;
     } else {
+        let suggestions = price_manager.get_item_suggestions(typed_name).await;
+        let did_you_mean = if suggestions.is_empty() {
+            String::new()
+        } else {
+            format!(" Did you mean: {}?", suggestions.iter().take(3).map(|name| format!("**{}**", name)).collect::<Vec<_>>().join(", "))
+        };
         command
             .create_response(&ctx.http, CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(format!("Item '{}' not found in price database.", item_name))
+                    .content(format!("Item '{}' not found in price database.{}", typed_name, did_you_mean))
             ))
             .await?;
     }
 
     Ok(())
-} 
\ No newline at end of file
+} 
+
+/// Records a drop and credits its points - straight from /drop, or once a mod approves it
+pub async fn record_drop(
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    submission: &Submission,
+) -> Result<(i64, rank_manager::PointsUpdate)> {
+    // The drop and its points go in together, so there's never one without the other
+    let mut tx = db.begin().await?;
+
+    // Insert or update user
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) 
+         VALUES (?, 0, 0)
+         ON CONFLICT(discord_id) DO NOTHING",
+        submission.discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Record the drop
+    let drop_id = sqlx::query!(
+        "INSERT INTO drops (discord_id, item_name, value, quantity, points, item_id, price_snapshot_id, evidence_url) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        submission.discord_id,
+        submission.item_name,
+        submission.value,
+        submission.quantity,
+        submission.points,
+        submission.item_id,
+        submission.price_snapshot_id,
+        submission.evidence_url
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    // Update total drops
+    sqlx::query!(
+        "UPDATE users 
+         SET total_drops = total_drops + ?
+         WHERE discord_id = ?",
+        submission.quantity,
+        submission.discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let (old_points, new_points) = rank_manager::record_points(
+        &mut tx,
+        &submission.discord_id,
+        submission.points,
+        "drop",
+        Some(&format!("drop #{}", drop_id))
+    ).await?;
+    tx.commit().await?;
+
+    // Log the drop
+    logger::log_action(
+        ctx,
+        &submission.discord_id,
+        "ADDED DROP",
+        &format!(
+            "{}x {} ({}){}",
+            submission.quantity,
+            submission.item_name,
+            format_gp(submission.value),
+            submission.evidence_url.as_deref().map(|url| format!(" [screenshot]({})", url)).unwrap_or_default()
+        )
+    ).await?;
+
+    // Check for rank up
+    let points_update = rank_manager::finish_points_update(
+        ctx,
+        &submission.discord_id,
+        &submission.user_name,
+        old_points,
+        new_points,
+        db
+    ).await?;
+
+    Ok((drop_id, points_update))
+}
+
+/// What the submitter is told once a drop has been credited
+pub fn drop_message(submission: &Submission, points_update: &rank_manager::PointsUpdate) -> String {
+    let quantity = submission.quantity;
+    let item_name = &submission.item_name;
+    let total_value = submission.value;
+    let points = submission.points;
+    if !points_update.crossed_ranks.is_empty() {
+        // User ranked up!
+        let rank_text = if points_update.crossed_ranks.len() == 1 {
+            format!("the {} rank", points_update.crossed_ranks[0])
+        } else {
+            let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
+            match ranks.len() {
+                2 => format!("the {} and {} ranks", ranks[0], ranks[1]),
+                _ => {
+                    let (last, rest) = ranks.split_last().unwrap();
+                    format!("the {}, and {} ranks", rest.join(", "), last)
+                }
+            }
+        };
+        
+        let next_rank_info = if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
+            format!(" Next rank at {} points for {}!", format_number(*next_rank_points), next_rank_name)
+        } else {
+            "".to_string()
+        };
+        
+        format!(
+            "🎆 🎇 **RANK UP!** 🎇 🎆\nDrop recorded: {}x {} ({}) (+{} points)! You now have {} and achieved {}!{}",
+            format_number(quantity),
+            item_name,
+            format_gp(total_value),
+            format_number(points),
+            format_points(points_update.new_points),
+            rank_text,
+            next_rank_info
+        )
+    } else if let Some((next_rank_points, next_rank_name)) = &points_update.next_rank {
+        format!(
+            "Drop recorded: {}x {} ({}) (+{} points)! You now have {}. Next rank at {} points for {}!",
+            format_number(quantity),
+            item_name,
+            format_gp(total_value),
+            format_number(points),
+            format_points(points_update.new_points),
+            format_number(*next_rank_points),
+            next_rank_name
+        )
+    } else {
+        format!(
+            "Drop recorded: {}x {} ({}) (+{} points)! You now have {}!",
+            format_number(quantity),
+            item_name,
+            format_gp(total_value),
+            format_number(points),
+            format_points(points_update.new_points)
+        )
+    }
+}
\ No newline at end of file
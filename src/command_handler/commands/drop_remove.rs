@@ -8,6 +8,7 @@ use sqlx::SqlitePool;
 use crate::command_handler::format_gp;
 use crate::rank_manager;
 use crate::logger;
+use super::undo::undo_hint;
 
 pub async fn handle_drop_remove(
     command: &CommandInteraction,
@@ -20,7 +21,7 @@ pub async fn handle_drop_remove(
     let recent_drops = sqlx::query!(
         "SELECT id, item_name, value, quantity, timestamp 
          FROM drops 
-         WHERE discord_id = ? 
+         WHERE discord_id = ? AND deleted_at IS NULL
          ORDER BY timestamp DESC 
          LIMIT 10",
         discord_id
@@ -80,128 +81,169 @@ pub async fn handle_drop_remove(
         }
     };
     
-    // Find the drop with the given ID
-    let drop_to_remove = sqlx::query!(
-        "SELECT id, item_name, value, quantity 
-         FROM drops 
-         WHERE id = ? AND discord_id = ?",
-        drop_id,
-        discord_id
-    )
-    .fetch_optional(db)
-    .await?;
-    
-    match drop_to_remove {
-        Some(drop) => {
-            // Calculate points to deduct
-            let points_to_deduct = drop.value / 100_000; // 1 point per 100,000 gp
-            
-            // Begin transaction
-            let mut tx = db.begin().await?;
-            
-            // Decrease user's total_drops
-            sqlx::query!(
-                "UPDATE users 
-                 SET total_drops = total_drops - ? 
-                 WHERE discord_id = ?",
-                drop.quantity,
-                discord_id
-            )
-            .execute(&mut *tx)
-            .await?;
-            
-            // Remove the drop
-            sqlx::query!(
-                "DELETE FROM drops WHERE id = ?",
-                drop.id
-            )
-            .execute(&mut *tx)
-            .await?;
-            
-            // Log the drop removal
-            logger::log_action(
-                ctx,
-                &discord_id,
-                "REMOVED DROP",
-                &format!("{}x {} ({}) [ID: {}]", drop.quantity, drop.item_name, format_gp(drop.value), drop.id)
-            ).await?;
-            
-            // Commit transaction
-            tx.commit().await?;
-            
-            // Deduct points from user
-            if points_to_deduct > 0 {
-                let points_update = rank_manager::add_points(
-                    ctx,
-                    &discord_id,
-                    &command.member.as_ref()
-                        .and_then(|m| Some(m.display_name()))
-                        .unwrap_or(&command.user.name),
-                    -points_to_deduct, // Negative to deduct points
-                    db
-                ).await?;
-                
-                let message = if !points_update.crossed_ranks.is_empty() {
-                    // User ranked down
-                    let rank_text = if points_update.crossed_ranks.len() == 1 {
-                        format!("You have lost the {} rank.", points_update.crossed_ranks[0])
-                    } else {
-                        let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
-                        match ranks.len() {
-                            2 => format!("You have lost the {} and {} ranks.", ranks[0], ranks[1]),
-                            _ => {
-                                let (last, rest) = ranks.split_last().unwrap();
-                                format!("You have lost the {}, and {} ranks.", rest.join(", "), last)
-                            }
-                        }
-                    };
-                    
-                    format!(
-                        "Drop removed: {}x {} ({}). {} points have been deducted from your total.\n⬇️ **RANK DOWN!** ⬇️\n{}",
-                        drop.quantity,
-                        drop.item_name,
-                        format_gp(drop.value),
-                        points_to_deduct,
-                        rank_text
-                    )
+    let user_name = command.member.as_ref()
+        .map(|m| m.display_name().to_string())
+        .unwrap_or_else(|| command.user.name.clone());
+    let reason = command.data.options
+        .iter()
+        .find(|opt| opt.name == "reason")
+        .and_then(|opt| opt.value.as_str());
+    let removed = remove_drop(ctx, db, &discord_id, &user_name, drop_id, &discord_id, reason).await?;
+    let found = removed.is_some();
+    let content = match removed {
+        Some((drop, Some(points_update))) => {
+            if !points_update.crossed_ranks.is_empty() {
+                // User ranked down
+                let rank_text = if points_update.crossed_ranks.len() == 1 {
+                    format!("You have lost the {} rank.", points_update.crossed_ranks[0])
                 } else {
-                    format!(
-                        "Drop removed: {}x {} ({}). {} points have been deducted from your total.",
-                        drop.quantity,
-                        drop.item_name,
-                        format_gp(drop.value),
-                        points_to_deduct
-                    )
+                    let ranks: Vec<_> = points_update.crossed_ranks.iter().map(|r| r.as_str()).collect();
+                    match ranks.len() {
+                        2 => format!("You have lost the {} and {} ranks.", ranks[0], ranks[1]),
+                        _ => {
+                            let (last, rest) = ranks.split_last().unwrap();
+                            format!("You have lost the {}, and {} ranks.", rest.join(", "), last)
+                        }
+                    }
                 };
                 
-                command
-                    .create_response(&ctx.http, CreateInteractionResponse::Message(
-                        CreateInteractionResponseMessage::new().content(message)
-                    ))
-                    .await?;
+                format!(
+                    "Drop removed: {}x {} ({}). {} points have been deducted from your total.\n⬇️ **RANK DOWN!** ⬇️\n{}",
+                    drop.quantity,
+                    drop.item_name,
+                    format_gp(drop.value),
+                    drop.points,
+                    rank_text
+                )
             } else {
-                command
-                    .create_response(&ctx.http, CreateInteractionResponse::Message(
-                        CreateInteractionResponseMessage::new()
-                            .content(format!(
-                                "Drop removed: {}x {} ({}). No points were deducted.",
-                                drop.quantity,
-                                drop.item_name,
-                                format_gp(drop.value)
-                            ))
-                    ))
-                    .await?;
+                format!(
+                    "Drop removed: {}x {} ({}). {} points have been deducted from your total.",
+                    drop.quantity,
+                    drop.item_name,
+                    format_gp(drop.value),
+                    drop.points
+                )
             }
         },
-        None => {
-            command
-                .create_response(&ctx.http, CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .content(format!("No drop found with ID {}.", drop_id))
-                ))
-                .await?;
-        }
-    }
+        Some((drop, None)) => format!(
+            "Drop removed: {}x {} ({}). No points were deducted.",
+            drop.quantity,
+            drop.item_name,
+            format_gp(drop.value)
+        ),
+        None => format!("No drop found with ID {}.", drop_id),
+    };
+
+    let content = if found { format!("{}\n{}", content, undo_hint()) } else { content };
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
     
     Ok(())
+}
+
+/// A drop that's just been taken off someone's record
+pub struct RemovedDrop {
+    pub id: i64,
+    pub item_name: String,
+    pub value: i64,
+    pub quantity: i64,
+    pub points: i64,
+}
+
+/// Removes one of a user's drops and takes its points back off them - from /drop_remove, or a
+/// mod's /admin_drop_remove. The row is only marked as deleted (by `removed_by`, for `reason`),
+/// so /undo can bring it back. None if they don't have a drop with that ID. The PointsUpdate is
+/// None if the drop wasn't worth any points.
+pub async fn remove_drop(
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    discord_id: &str,
+    user_name: &str,
+    drop_id: i64,
+    removed_by: &str,
+    reason: Option<&str>,
+) -> Result<Option<(RemovedDrop, Option<rank_manager::PointsUpdate>)>> {
+    // Find the drop with the given ID
+    let Some(drop) = sqlx::query_as!(
+        RemovedDrop,
+        "SELECT id, item_name, value, quantity, points
+         FROM drops 
+         WHERE id = ? AND discord_id = ? AND deleted_at IS NULL",
+        drop_id,
+        discord_id
+    )
+    .fetch_optional(db)
+    .await? else {
+        return Ok(None);
+    };
+
+    // The removal, total_drops and the points coming off all go in together
+    let mut tx = db.begin().await?;
+
+    // Remove the drop - unless it's been removed since it was looked up (a double click, or a mod
+    // at the same time), so the points don't come off twice
+    let removed = sqlx::query!(
+        "UPDATE drops SET deleted_at = CURRENT_TIMESTAMP, deleted_by = ?, delete_reason = ? WHERE id = ? AND deleted_at IS NULL",
+        removed_by,
+        reason,
+        drop.id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if removed == 0 {
+        return Ok(None);
+    }
+
+    // Decrease user's total_drops
+    sqlx::query!(
+        "UPDATE users 
+         SET total_drops = total_drops - ? 
+         WHERE discord_id = ?",
+        drop.quantity,
+        discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Deduct points from user - whatever it was worth when it was recorded, whatever the rules say now
+    let totals = if drop.points > 0 {
+        Some(rank_manager::record_points(
+            &mut tx,
+            discord_id,
+            -drop.points, // Negative to deduct points
+            "drop_removed",
+            Some(&format!("drop #{}", drop.id))
+        ).await?)
+    } else {
+        None
+    };
+
+    // Commit transaction
+    tx.commit().await?;
+
+    // Log the drop removal
+    logger::log_action(
+        ctx,
+        discord_id,
+        "REMOVED DROP",
+        &format!(
+            "{}x {} ({}) [ID: {}]{}",
+            drop.quantity,
+            drop.item_name,
+            format_gp(drop.value),
+            drop.id,
+            reason.map(|reason| format!(" - {}", reason)).unwrap_or_default()
+        )
+    ).await?;
+
+    let Some((old_points, new_points)) = totals else {
+        return Ok(Some((drop, None)));
+    };
+    let points_update = rank_manager::finish_points_update(ctx, discord_id, user_name, old_points, new_points, db).await?;
+    Ok(Some((drop, Some(points_update))))
 } 
\ No newline at end of file
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{format_duration, format_number};
+
+fn boss_option(command: &CommandInteraction) -> Result<&str> {
+    command.data.options
+        .iter()
+        .find(|opt| opt.name == "boss")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Boss not provided"))
+}
+
+pub async fn handle_kc(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let boss = boss_option(command)?;
+    // Anyone can look anyone up, but default to whoever asked
+    let user_id = command.data.options
+        .iter()
+        .find(|opt| opt.name == "user")
+        .and_then(|opt| opt.value.as_user_id())
+        .unwrap_or(command.user.id);
+    let discord_id = user_id.to_string();
+
+    let accounts = sqlx::query!(
+        "SELECT runescape_name, boss, kill_count, personal_best_ms, personal_best_at
+         FROM kill_counts
+         WHERE discord_id = ? AND boss = ? COLLATE NOCASE
+         ORDER BY kill_count DESC",
+        discord_id,
+        boss
+    )
+    .fetch_all(db)
+    .await?;
+
+    if accounts.is_empty() {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("<@{}> has no {} kills recorded yet. Kill counts come from Dink's kill count notifications.", discord_id, boss))
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    let boss_name = accounts[0].boss.clone();
+    let mut embed = CreateEmbed::new()
+        .title(format!("{} Kill Count", boss_name))
+        .color(0x00ff00);
+
+    for account in &accounts {
+        let personal_best = match account.personal_best_ms {
+            Some(ms) => {
+                // Where this PB sits among everyone in the clan
+                let clan_rank = sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) as "count!: i64" FROM kill_counts WHERE boss = ? AND personal_best_ms < ?"#,
+                    account.boss,
+                    ms
+                )
+                .fetch_one(db)
+                .await? + 1;
+                format!("\n• PB: {} (#{} in the clan)", format_duration(ms), clan_rank)
+            }
+            None => String::new(),
+        };
+        embed = embed.field(
+            account.runescape_name.clone(),
+            format!("• Kills: {}{}", format_number(account.kill_count), personal_best),
+            true
+        );
+    }
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_pb_board(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let boss = boss_option(command)?;
+
+    // One entry per account, fastest first; ties go to whoever got there first
+    let records = sqlx::query!(
+        r#"SELECT runescape_name, discord_id, boss, kill_count, personal_best_ms as "personal_best_ms!", personal_best_at
+           FROM kill_counts
+           WHERE boss = ? COLLATE NOCASE AND personal_best_ms IS NOT NULL
+           ORDER BY personal_best_ms ASC, personal_best_at ASC
+           LIMIT 10"#,
+        boss
+    )
+    .fetch_all(db)
+    .await?;
+
+    if records.is_empty() {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("Nobody has a {} personal best recorded yet.", boss))
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    let board = records.iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let place = match i {
+                0 => "🥇".to_string(),
+                1 => "🥈".to_string(),
+                2 => "🥉".to_string(),
+                _ => format!("{}.", i + 1),
+            };
+            format!(
+                "{} **{}** - {} (<@{}>, {} kc{})",
+                place,
+                format_duration(record.personal_best_ms),
+                record.runescape_name,
+                record.discord_id,
+                format_number(record.kill_count),
+                record.personal_best_at.map(|at| format!(", {}", at.format("%Y-%m-%d"))).unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title(format!("🏆 {} Hall of Fame", records[0].boss))
+        .description(board)
+        .footer(CreateEmbedFooter::new("Personal bests from Dink kill count notifications"))
+        .color(0xffd700);
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
@@ -19,6 +19,7 @@ pub async fn handle_leaderboard(
         r#"WITH user_clogs AS (
             SELECT discord_id, COUNT(*) as count
             FROM collection_log_entries
+            WHERE deleted_at IS NULL
             GROUP BY discord_id
         )
         SELECT u.discord_id, u.points, u.total_drops, COALESCE(c.count, 0) as clog_count
@@ -38,25 +39,26 @@ pub async fn handle_leaderboard(
                    SUM(value) as total_value,
                    MAX(value) as best_drop_value
             FROM drops
-            WHERE timestamp >= datetime('now', '-30 days')
+            WHERE timestamp >= datetime('now', '-30 days') AND deleted_at IS NULL
             GROUP BY discord_id
             ORDER BY total_value DESC
             LIMIT 5
         ),
         best_drops AS (
-            SELECT d1.discord_id, d1.item_name as best_drop_name
+            SELECT d1.discord_id, COALESCE(n.item_name, d1.item_name) as best_drop_name
             FROM drops d1
             JOIN monthly_drops m ON d1.discord_id = m.discord_id
-            WHERE d1.timestamp >= datetime('now', '-30 days')
+            LEFT JOIN v_drop_item_names n ON n.item_id = d1.item_id
+            WHERE d1.timestamp >= datetime('now', '-30 days') AND d1.deleted_at IS NULL
             AND d1.value = (
                 SELECT MAX(value)
                 FROM drops d2
                 WHERE d2.discord_id = d1.discord_id
-                AND d2.timestamp >= datetime('now', '-30 days')
+                AND d2.timestamp >= datetime('now', '-30 days') AND d2.deleted_at IS NULL
             )
             GROUP BY d1.discord_id
         )
-        SELECT m.discord_id, m.drop_count, m.total_value, m.best_drop_value, b.best_drop_name, u.points
+        SELECT m.discord_id, m.drop_count, m.total_value, m.best_drop_value, b.best_drop_name as "best_drop_name: String", u.points
         FROM monthly_drops m
         LEFT JOIN best_drops b ON m.discord_id = b.discord_id
         JOIN users u ON m.discord_id = u.discord_id"#
@@ -72,7 +74,7 @@ pub async fn handle_leaderboard(
                    SUM(points) as total_points,
                    MAX(points) as best_entry_points
             FROM collection_log_entries
-            WHERE timestamp >= datetime('now', '-30 days')
+            WHERE timestamp >= datetime('now', '-30 days') AND deleted_at IS NULL
             GROUP BY discord_id
             ORDER BY entry_count DESC
             LIMIT 5
@@ -81,12 +83,12 @@ pub async fn handle_leaderboard(
             SELECT c1.discord_id, c1.item_name as best_entry_name
             FROM collection_log_entries c1
             JOIN monthly_clogs m ON c1.discord_id = m.discord_id
-            WHERE c1.timestamp >= datetime('now', '-30 days')
+            WHERE c1.timestamp >= datetime('now', '-30 days') AND c1.deleted_at IS NULL
             AND c1.points = (
                 SELECT MAX(points)
                 FROM collection_log_entries c2
                 WHERE c2.discord_id = c1.discord_id
-                AND c2.timestamp >= datetime('now', '-30 days')
+                AND c2.timestamp >= datetime('now', '-30 days') AND c2.deleted_at IS NULL
             )
             GROUP BY c1.discord_id
         )
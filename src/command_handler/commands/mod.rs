@@ -9,6 +9,18 @@ pub mod rsname;
 pub mod rsname_remove;
 pub mod rsnames;
 pub mod moderation;
+pub mod ranks;
+pub mod dink_replay;
+pub mod achievements;
+pub mod kill_counts;
+pub mod point_rules;
+pub mod clog_curve;
+pub mod price_audit;
+pub mod price_override;
+pub mod approval;
+pub mod admin;
+pub mod undo;
+pub mod permissions;
 
 pub use drop::handle_drop;
 pub use clog::handle_clog;
@@ -22,4 +34,17 @@ pub use rsname_remove::handle_rsname_remove;
 pub use rsnames::handle_rsnames; 
 pub use moderation::handle_recalculate;
 pub use moderation::handle_clamp;
-pub use moderation::handle_whitelist;
\ No newline at end of file
+pub use moderation::handle_whitelist;
+pub use moderation::handle_audit_points;
+pub use ranks::{handle_rank_add, handle_rank_edit, handle_rank_list, handle_rank_remove};
+pub use dink_replay::handle_dink_replay;
+pub use achievements::handle_achievement_points;
+pub use kill_counts::{handle_kc, handle_pb_board};
+pub use point_rules::handle_point_rules;
+pub use clog_curve::handle_clog_curve;
+pub use price_audit::handle_price_audit;
+pub use price_override::handle_price_override;
+pub use approval::{handle_approval_button, APPROVAL_PREFIX};
+pub use admin::{handle_admin_drop_add, handle_admin_drop_remove, handle_admin_clog_add, handle_admin_clog_remove, handle_admin_points_adjust};
+pub use undo::{handle_undo, UNDO_WINDOW_MINUTES};
+pub use permissions::{handle_command_permissions, is_allowed};
\ No newline at end of file
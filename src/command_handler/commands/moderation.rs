@@ -9,12 +9,16 @@ use serenity::all::{
     EditInteractionResponse,
 };
 use sqlx::SqlitePool;
-use crate::command_handler::CollectionLogManagerKey;
+use crate::command_handler::{CollectionLogManagerKey, format_number};
 use crate::rank_manager;
 use crate::logger;
-use crate::runescape_tracker::RunescapeTrackerKey;
+use crate::runescape_tracker::{RunescapeTracker, RunescapeTrackerKey};
+use crate::scoring;
 use sqlx::{QueryBuilder, Row, Sqlite};
 
+// What /clamp caps a category at when no max is given - the cap every clamped category used to share
+const DEFAULT_CLAMP: i64 = 3000;
+
 pub struct ItemData {
     item_id: i64,
     item_name: String,
@@ -30,12 +34,62 @@ pub struct ClogRow {
     id: i64,
     discord_id: String,
     points: i64,
-    item_name: String,
+    item_id: Option<i64>,
 }
 
 pub struct PlayerStats {
-    change: i64,
-    name: String,
+    pub change: i64,
+    pub name: String,
+}
+
+// Post-commit lookups keep the transaction short and avoid external/API latency in tx.
+pub async fn notify_recalculated_players(
+    ctx: &serenity::prelude::Context,
+    rs_manager: &RunescapeTracker,
+    affected_player_deltas: HashMap<String, i64>,
+    db: &SqlitePool,
+) -> HashMap<String, PlayerStats> {
+    let mut affected_players = HashMap::new();
+    for (discord_id, point_delta) in affected_player_deltas.into_iter() {
+        let rs_name = rs_manager
+            .get_username_from_discord_id(ctx, discord_id.as_str())
+            .await
+            .unwrap_or_else(|_| "Unknown user".to_string());
+
+        let new_points = sqlx::query("SELECT points FROM users WHERE discord_id = ?")
+            .bind(discord_id.as_str())
+            .fetch_one(db)
+            .await
+            .map(|row| row.get::<i64, _>("points"))
+            .unwrap_or(0);
+        let old_points = new_points - point_delta;
+
+        if let Err(err) = rank_manager::notify_rank_transition(
+            ctx,
+            discord_id.as_str(),
+            &rs_name,
+            old_points,
+            new_points,
+            db,
+        )
+        .await
+        {
+            tracing::error!(
+                "Failed to emit rank transition notification for {}: {:?}",
+                discord_id,
+                err
+            );
+        }
+
+        affected_players.insert(
+            discord_id,
+            PlayerStats {
+                change: point_delta,
+                name: rs_name,
+            },
+        );
+    }
+    affected_players
 }
 
 pub async fn handle_recalculate( //Big red button
@@ -49,18 +103,26 @@ pub async fn handle_recalculate( //Big red button
                 .content("Recalculating...")
         ))
         .await?;
+    let drops = command.data.options
+        .iter()
+        .find(|opt| opt.name == "drops")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+    if drops {
+        return recalculate_drops(command, ctx, db).await;
+    }
     let data = ctx.data.read().await;
     //This query assumes:
     //Item should have a non-zero amount of clogs for us to care about it
-    //Clamps may have been removed or added, and we want to fix any problem clogs
+    //Clamps may have been removed, added or changed, and we want to fix any problem clogs
     //Whitelists may have been removed or added, same reason
     //Only low completion percentage clogs are an issue, so we check all those (a clamp may have been removed instead of adding to whitelist)
     let item_records = sqlx::query!(
         //I have to list every column to remove type inference issues ughhhhhhhhh
-        "SELECT item_id, item_name, preferred_name, categories, percentage, highest_points as 'highest_points!: i64', whitelist, clog_count, clamp, clamped_category from v_item_data
-        WHERE clog_count > 0 AND ((clamp = 1 AND highest_points > 3000)
+        "SELECT item_id, item_name, preferred_name, categories, percentage, highest_points as 'highest_points!: i64', whitelist, clog_count, clamp, clamped_category, point_cap from v_item_data
+        WHERE clog_count > 0 AND ((point_cap IS NOT NULL AND highest_points > point_cap)
         OR whitelist = 1 OR percentage < 10)
-        GROUP BY item_name ORDER BY percentage" //Until we work off item_id we gotta take care of dupes. Assume it's the most acquired one
+        ORDER BY percentage"
     )
     .fetch_all(db)
     .await?;
@@ -75,22 +137,22 @@ pub async fn handle_recalculate( //Big red button
         let rs_manager = data.get::<RunescapeTrackerKey>().unwrap();
 
         let mut clog_query: QueryBuilder<Sqlite> = QueryBuilder::new(
-            "SELECT * FROM collection_log_entries WHERE item_name IN (",
+            "SELECT * FROM collection_log_entries WHERE deleted_at IS NULL AND item_id IN (",
         );
 
         let mut clog_query_separated = clog_query.separated(", ");
         for (i, record) in item_records.iter().enumerate() {
 
-            clog_query_separated.push(format!("\"{}\"", record.item_name.clone()));
+            clog_query_separated.push_bind(record.item_id);
 
             let old_points: i64 = record.highest_points;
             item_vector.push(ItemData {
                 item_id: record.item_id,
                 item_name: record.item_name.clone(),
                 percentage: record.percentage.clone().parse::<f64>().unwrap(),
-                clamp: if record.clamp == 1 && record.whitelist == 0i64 { true } else { false },
+                clamp: record.point_cap.is_some(),
                 old_points: old_points,
-                points: clog_manager.calculate_points(record.item_name.clone().as_str()).await.unwrap(),
+                points: clog_manager.calculate_points(record.item_id).await.unwrap(),
                 affected: 0,
             }); 
         }
@@ -105,19 +167,20 @@ pub async fn handle_recalculate( //Big red button
         if clog_records.len() > 0 {
 
             let mut affected_player_deltas: HashMap<String, i64> = HashMap::new();
-            let mut pending_clog_updates: Vec<(i64, i64)> = Vec::new(); // (entry_id, new_points)
+            let mut pending_clog_updates: Vec<(i64, String, i64, i64)> = Vec::new(); // (entry_id, discord_id, point_delta, new_points)
 
             
 
             for (i, row) in clog_records.into_iter().enumerate() {
-                let target_item = item_vector.iter().position(|item| item.item_name == row.item_name).unwrap();
+                let target_item = item_vector.iter().position(|item| Some(item.item_id) == row.item_id).unwrap();
 
                 let point_delta = item_vector[target_item].points - row.points; //Positive if new number bigger, negative otherwise
 
                 let discord_id = row.discord_id;
 
                 //Only lower points if we're clamping it. We don't want to lower points if we don't have to
-                if ((point_delta.is_negative()) && item_vector[target_item].clamp) || ((point_delta.is_positive()) && !item_vector[target_item].clamp) {
+                //Clamped items can still go up, if their category's cap was raised
+                if ((point_delta.is_negative()) && item_vector[target_item].clamp) || point_delta.is_positive() {
                     running_count += 1;
 
                     tracing::info!("User {} point change from {}: {}", discord_id, item_vector[target_item].item_name, point_delta);
@@ -127,7 +190,7 @@ pub async fn handle_recalculate( //Big red button
                         .and_modify(|player_delta| *player_delta += point_delta)
                         .or_insert(point_delta);
 
-                    pending_clog_updates.push((row.id, item_vector[target_item].points));
+                    pending_clog_updates.push((row.id, discord_id, point_delta, item_vector[target_item].points));
 
                     item_vector[target_item].affected += 1;
                 }
@@ -139,74 +202,27 @@ pub async fn handle_recalculate( //Big red button
             if running_count > 0 {
                 let mut tx = db.begin().await?;
 
-                for (entry_id, new_points) in &pending_clog_updates {
+                // One ledger row per changed entry, so each adjustment can be traced back to its clog
+                for (entry_id, discord_id, point_delta, new_points) in &pending_clog_updates {
                     sqlx::query("UPDATE collection_log_entries SET points = ? WHERE id = ?")
                         .bind(*new_points)
                         .bind(*entry_id)
                         .execute(&mut *tx)
                         .await?;
-                }
 
-                for (discord_id, point_delta) in &affected_player_deltas {
-                    // Ensure user row exists before applying the delta.
-                    sqlx::query(
-                        "INSERT INTO users (discord_id, points, total_drops) VALUES (?, 0, 0)
-                         ON CONFLICT(discord_id) DO NOTHING",
+                    rank_manager::record_points(
+                        &mut tx,
+                        discord_id,
+                        *point_delta,
+                        "recalculation",
+                        Some(&format!("clog #{}", entry_id)),
                     )
-                    .bind(discord_id)
-                    .execute(&mut *tx)
                     .await?;
-
-                    sqlx::query("UPDATE users SET points = points + ? WHERE discord_id = ?")
-                        .bind(*point_delta)
-                        .bind(discord_id)
-                        .execute(&mut *tx)
-                        .await?;
                 }
 
                 tx.commit().await?;
 
-                // Post-commit lookups keep the transaction short and avoid external/API latency in tx.
-                let mut affected_players = HashMap::new();
-                for (discord_id, point_delta) in affected_player_deltas.into_iter() {
-                    let rs_name = rs_manager
-                        .get_username_from_discord_id(ctx, discord_id.as_str())
-                        .await
-                        .unwrap_or_else(|_| "Unknown user".to_string());
-
-                    let new_points = sqlx::query("SELECT points FROM users WHERE discord_id = ?")
-                        .bind(discord_id.as_str())
-                        .fetch_one(db)
-                        .await
-                        .map(|row| row.get::<i64, _>("points"))
-                        .unwrap_or(0);
-                    let old_points = new_points - point_delta;
-
-                    if let Err(err) = rank_manager::notify_rank_transition(
-                        ctx,
-                        discord_id.as_str(),
-                        &rs_name,
-                        old_points,
-                        new_points,
-                        db,
-                    )
-                    .await
-                    {
-                        tracing::error!(
-                            "Failed to emit rank transition notification for {}: {:?}",
-                            discord_id,
-                            err
-                        );
-                    }
-
-                    affected_players.insert(
-                        discord_id,
-                        PlayerStats {
-                            change: point_delta,
-                            name: rs_name,
-                        },
-                    );
-                }
+                let affected_players = notify_recalculated_players(ctx, rs_manager, affected_player_deltas, db).await;
 
                 let mut info_readout = format!("\n**Recalculation Results** (only highest points previously awarded listed):\n{} total records affected!", running_count);
 
@@ -252,6 +268,90 @@ pub async fn handle_recalculate( //Big red button
     Ok(())
 }
 
+// Reapplies the current point_rules to every drop on record, in both directions - unlike clogs,
+// a changed rate is meant to change what old drops are worth
+async fn recalculate_drops(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let rules = scoring::PointRules::load(db).await?;
+    let drops = sqlx::query!("SELECT id, discord_id, item_name, value, points FROM drops WHERE deleted_at IS NULL")
+        .fetch_all(db)
+        .await?;
+
+    let mut affected_player_deltas: HashMap<String, i64> = HashMap::new();
+    let mut affected_items: HashMap<String, (i64, i64)> = HashMap::new(); // item -> (drops affected, point delta)
+    let mut pending_drop_updates: Vec<(i64, String, i64, i64)> = Vec::new(); // (drop_id, discord_id, point_delta, new_points)
+    for drop in drops {
+        let new_points = rules.for_item(&drop.item_name).points(drop.value);
+        let point_delta = new_points - drop.points;
+        if point_delta == 0 {
+            continue;
+        }
+        *affected_player_deltas.entry(drop.discord_id.clone()).or_insert(0) += point_delta;
+        let item = affected_items.entry(drop.item_name).or_insert((0, 0));
+        item.0 += 1;
+        item.1 += point_delta;
+        pending_drop_updates.push((drop.id, drop.discord_id, point_delta, new_points));
+    }
+
+    tracing::info!("Total edited drop count: {}", pending_drop_updates.len());
+    if pending_drop_updates.is_empty() {
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content("Nothing to report, sheriff!"))
+            .await?;
+        return Ok(());
+    }
+
+    let mut tx = db.begin().await?;
+    // One ledger row per changed drop, same as clogs
+    for (drop_id, discord_id, point_delta, new_points) in &pending_drop_updates {
+        sqlx::query!("UPDATE drops SET points = ? WHERE id = ?", new_points, drop_id)
+            .execute(&mut *tx)
+            .await?;
+
+        rank_manager::record_points(
+            &mut tx,
+            discord_id,
+            *point_delta,
+            "recalculation",
+            Some(&format!("drop #{}", drop_id)),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let data = ctx.data.read().await;
+    let rs_manager = data.get::<RunescapeTrackerKey>().unwrap();
+    let affected_players = notify_recalculated_players(ctx, rs_manager, affected_player_deltas, db).await;
+
+    let mut info_readout = format!("\n**Drop Recalculation Results**:\n{} total drops affected!", pending_drop_updates.len());
+    for (item_name, (affected, point_delta)) in affected_items {
+        info_readout += format!("\n**{}**: {} drops, **{}{}** points",
+            item_name,
+            affected,
+            if point_delta.is_positive() {"+"} else {""},
+            point_delta).as_str();
+    }
+    info_readout += "\n**Affected users:**";
+    for (discord_id, player) in affected_players {
+        info_readout += format!("\n**{}** ({}): **{}{}** points",
+            player.name,
+            discord_id,
+            if player.change.is_positive() {"+"} else {""},
+            player.change,
+        ).as_str();
+    }
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content("Recalculation Complete!"))
+        .await?;
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "recalculate", &info_readout).await?;
+    Ok(())
+}
+
 pub async fn handle_clamp(
     command: &CommandInteraction,
     ctx: &serenity::prelude::Context,
@@ -265,14 +365,30 @@ pub async fn handle_clamp(
         .and_then(|opt| opt.value.as_str())
         .ok_or_else(|| anyhow::anyhow!("Category name not provided"))?;
 
-    let one_or_zero = if on_or_off {1} else {0};
+    //clamp is the category's cap in points, 0 when it's unclamped
+    let max_points = if on_or_off {
+        options
+            .iter()
+            .find(|opt| opt.name == "max")
+            .and_then(|opt| opt.value.as_i64())
+            .unwrap_or(DEFAULT_CLAMP)
+    } else {0};
 
-    sqlx::query!("UPDATE category_table SET clamp=? WHERE category=?", one_or_zero, category_name)
+    let result = sqlx::query!("UPDATE category_table SET clamp=? WHERE category=?", max_points, category_name)
     .execute(db).await?;
+    if result.rows_affected() == 0 {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("There's no collection log category called {}.", category_name))
+            ))
+            .await?;
+        return Ok(());
+    }
 
-    let response_string = format!("{} is now {}", category_name, if one_or_zero == 1 {
-        "clamped! Items in this category will only give a maximum of 3000 points."
-    } else {"unclamped! Items in this category can go beyond 3000 points!"});
+    let response_string = format!("{} is now {}\nRun `/recalculate` to apply it to clogs already recorded.", category_name, if on_or_off {
+        format!("clamped! Items in this category will only give a maximum of {} points.", format_number(max_points))
+    } else {"unclamped! Items in this category are no longer capped (unless another category they're in is)!".to_string()});
 
     command
                 .create_response(&ctx.http, CreateInteractionResponse::Message(
@@ -281,7 +397,8 @@ pub async fn handle_clamp(
                 ))
                 .await?;
     let commanding_officer_id = command.user.id.to_string();
-    logger::log_action(ctx, &commanding_officer_id, if one_or_zero == 1 {"CLAMPED"} else {"UNCLAMPED"}, &category_name).await?;
+    let details = if on_or_off { format!("{} (max {} points)", category_name, max_points) } else { category_name.to_string() };
+    logger::log_action(ctx, &commanding_officer_id, if on_or_off {"CLAMPED"} else {"UNCLAMPED"}, &details).await?;
     Ok(())
 }
 
@@ -319,22 +436,72 @@ pub async fn handle_whitelist( //These are both basically the same function but
     Ok(())
 }
 
-// async fn is_allowed( //I wrote this and then found out you can specify required perms
-//     command: &CommandInteraction,
-//     ctx: &serenity::prelude::Context,
-// ) -> Result<bool> {
-//     let config = Config::from_env()?;
-//     let admin_role = config.admin_role_id;
-//     let allowed = command.member.unwrap().roles.contains(&admin_role.unwrap());
-
-//     if !allowed {
-//         command
-//                 .create_response(&ctx.http, CreateInteractionResponse::Message(
-//                     CreateInteractionResponseMessage::new()
-//                         .content("Sorry kitten, you're not allowed to do that.")
-//                 ))
-//                 .await?;
-//     }
-
-//     Ok(allowed)
-// }
\ No newline at end of file
+pub async fn handle_audit_points(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let fix = command.data.options
+        .iter()
+        .find(|opt| opt.name == "fix")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("Auditing points...")
+        ))
+        .await?;
+
+    let discrepancies = rank_manager::audit_points(db).await?;
+    if discrepancies.is_empty() {
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content("Everyone's points add up, sheriff!"))
+            .await?;
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let rs_manager = data.get::<RunescapeTrackerKey>().unwrap();
+
+    let mut info_readout = format!("**Points Audit**: {} users don't match their drops and clogs{}",
+        discrepancies.len(),
+        if fix {" (fixed)"} else {""});
+
+    for user in &discrepancies {
+        let point_delta = user.expected_points - user.stored_points;
+        info_readout += format!("\n<@{}>: stored {}, expected {} ({} from drops + {} from clogs + {} from achievements + {} from adjustments) (**{}{}**)",
+            user.discord_id,
+            format_number(user.stored_points),
+            format_number(user.expected_points),
+            format_number(user.drop_points),
+            format_number(user.clog_points),
+            format_number(user.achievement_points),
+            format_number(user.adjustment_points),
+            if point_delta.is_positive() {"+"} else {""},
+            point_delta).as_str();
+
+        if fix {
+            let user_name = rs_manager
+                .get_username_from_discord_id(ctx, &user.discord_id)
+                .await
+                .unwrap_or_else(|_| "Unknown user".to_string());
+            // Goes through add_points so the correction lands in the ledger and any rank change
+            // gets announced like any other
+            rank_manager::add_points(ctx, &user.discord_id, &user_name, point_delta, "reconcile", None, db).await?;
+        }
+    }
+
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, if fix {"RECONCILED POINTS"} else {"AUDITED POINTS"}, &info_readout).await?;
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(format!(
+            "{} users don't match their drops and clogs{}. Details are in the log channel.",
+            discrepancies.len(),
+            if fix {", and have been fixed"} else {" - run `/audit_points fix:True` to fix them"}
+        )))
+        .await?;
+    Ok(())
+}
\ No newline at end of file
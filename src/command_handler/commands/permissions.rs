@@ -0,0 +1,283 @@
+use anyhow::Result;
+use serenity::all::{
+    Command,
+    CommandInteraction,
+    CreateAllowedMentions,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    Member,
+};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use crate::config::ConfigKey;
+use crate::logger;
+
+/// Who a command is open to. Each tier includes the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Everyone,
+    Moderator,
+    Admin,
+}
+
+impl Tier {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Tier::Everyone => "everyone",
+            Tier::Moderator => "moderator",
+            Tier::Admin => "admin",
+        }
+    }
+
+    fn parse(tier: &str) -> Option<Self> {
+        match tier {
+            "everyone" => Some(Tier::Everyone),
+            "moderator" => Some(Tier::Moderator),
+            "admin" => Some(Tier::Admin),
+            _ => None,
+        }
+    }
+}
+
+// The Administrator permission or ADMIN_ROLE_ID makes you an admin, MODERATOR_ROLE_ID a moderator.
+// Outside a server (no member) everyone's just everyone.
+async fn member_tier(ctx: &serenity::prelude::Context, member: Option<&Member>) -> Tier {
+    let Some(member) = member else {
+        return Tier::Everyone;
+    };
+    if member.permissions.is_some_and(|permissions| permissions.administrator()) {
+        return Tier::Admin;
+    }
+    let data = ctx.data.read().await;
+    let Some(config) = data.get::<ConfigKey>() else {
+        return Tier::Everyone;
+    };
+    if config.admin_role_id.is_some_and(|role| member.roles.contains(&role)) {
+        Tier::Admin
+    } else if config.moderator_role_id.is_some_and(|role| member.roles.contains(&role)) {
+        Tier::Moderator
+    } else {
+        Tier::Everyone
+    }
+}
+
+// Everything seeded into command_permissions. These stay admin-only if their row goes missing
+// (or holds something unreadable), rather than opening up to everyone
+const STAFF_COMMANDS: &[&str] = &[
+    "clamp",
+    "unclamp",
+    "whitelist",
+    "unwhitelist",
+    "price_audit",
+    "rank_list",
+    "admin_drop_add",
+    "admin_drop_remove",
+    "admin_clog_add",
+    "admin_clog_remove",
+    "approval",
+    "recalculate",
+    "point_rules",
+    "price_override",
+    "clog_curve",
+    "audit_points",
+    "admin_points_adjust",
+    "rank_add",
+    "rank_remove",
+    "rank_edit",
+    "dink_replay",
+    "achievement_points",
+    "command_permissions",
+];
+
+// What a command needs when command_permissions doesn't say
+fn default_tier(command: &str) -> Tier {
+    if STAFF_COMMANDS.contains(&command) {
+        Tier::Admin
+    } else {
+        Tier::Everyone
+    }
+}
+
+/// The tier a command needs - whatever command_permissions says, otherwise admin for staff
+/// commands and everyone for the rest
+pub async fn required_tier(db: &SqlitePool, command: &str) -> Result<Tier> {
+    if command == "command_permissions" {
+        return Ok(Tier::Admin);
+    }
+    let tier = sqlx::query_scalar!("SELECT tier FROM command_permissions WHERE command = ?", command)
+        .fetch_optional(db)
+        .await?;
+    Ok(tier.as_deref().and_then(Tier::parse).unwrap_or_else(|| default_tier(command)))
+}
+
+/// Whether a member can use a command (or, for `approval`, the Approve/Deny buttons)
+pub async fn is_allowed(
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    member: Option<&Member>,
+    command: &str,
+) -> Result<bool> {
+    let required = required_tier(db, command).await?;
+    Ok(required == Tier::Everyone || member_tier(ctx, member).await >= required)
+}
+
+// The list mentions the staff roles, which shouldn't ping everyone in them
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .allowed_mentions(CreateAllowedMentions::new())
+        ))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_command_permissions(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let command_name = options
+        .iter()
+        .find(|opt| opt.name == "command")
+        .and_then(|opt| opt.value.as_str())
+        .map(|name| name.trim().trim_start_matches('/').to_lowercase());
+    let tier = options
+        .iter()
+        .find(|opt| opt.name == "tier")
+        .and_then(|opt| opt.value.as_str())
+        .and_then(Tier::parse);
+
+    let (Some(command_name), Some(tier)) = (command_name, tier) else {
+        return list_command_permissions(command, ctx, db).await;
+    };
+
+    // Handing this one out would let whoever gets it raise their own access
+    if command_name == "command_permissions" {
+        return respond(command, ctx, "`/command_permissions` is always admin-only.".to_string()).await;
+    }
+    let known = command_name == "approval" || Command::get_global_commands(&ctx.http)
+        .await?
+        .iter()
+        .any(|registered| registered.name == command_name);
+    if !known {
+        return respond(command, ctx, format!("There's no `/{}` command.", command_name)).await;
+    }
+
+    // Kept even for everyone - a staff command without a row goes back to admin-only
+    let tier_name = tier.as_str();
+    sqlx::query!(
+        "INSERT INTO command_permissions (command, tier) VALUES (?, ?)
+         ON CONFLICT(command) DO UPDATE SET tier = excluded.tier",
+        command_name,
+        tier_name
+    )
+    .execute(db)
+    .await?;
+
+    let details = format!("/{} is now open to {}", command_name, match tier {
+        Tier::Everyone => "everyone",
+        Tier::Moderator => "moderators and admins",
+        Tier::Admin => "admins only",
+    });
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "COMMAND PERMISSION", &details).await?;
+    respond(command, ctx, format!("{}.", details)).await
+}
+
+async fn list_command_permissions(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let rows = sqlx::query!(r#"SELECT command as "command!", tier FROM command_permissions"#)
+        .fetch_all(db)
+        .await?;
+
+    // Staff commands without a row count as admin-only, same as required_tier
+    let mut tiers: BTreeMap<String, Tier> = STAFF_COMMANDS.iter()
+        .map(|command| (command.to_string(), Tier::Admin))
+        .collect();
+    for row in rows {
+        let tier = Tier::parse(&row.tier).unwrap_or_else(|| default_tier(&row.command));
+        tiers.insert(row.command, tier);
+    }
+    tiers.insert("command_permissions".to_string(), Tier::Admin);
+
+    let mut content = String::from("**Command permissions** (anything not listed is open to everyone):");
+    for tier in [Tier::Admin, Tier::Moderator] {
+        let commands: Vec<String> = tiers.iter()
+            .filter(|(_, command_tier)| **command_tier == tier)
+            .map(|(command, _)| if command == "approval" {
+                "the Approve/Deny buttons (`approval`)".to_string()
+            } else {
+                format!("`/{}`", command)
+            })
+            .collect();
+        if !commands.is_empty() {
+            content += &format!("\n**{}**: {}", tier.as_str(), commands.join(", "));
+        }
+    }
+
+    let (moderator_role_id, admin_role_id) = {
+        let data = ctx.data.read().await;
+        data.get::<ConfigKey>()
+            .map(|config| (config.moderator_role_id, config.admin_role_id))
+            .unwrap_or_default()
+    };
+    content += &format!(
+        "\nModerator role: {}. Admin role: {} (anyone with the Administrator permission counts as an admin).",
+        moderator_role_id.map_or("not set".to_string(), |role| format!("<@&{}>", role)),
+        admin_role_id.map_or("not set".to_string(), |role| format!("<@&{}>", role))
+    );
+    content += "\nChange one with `/command_permissions <command> <tier>`.";
+
+    respond(command, ctx, content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_round_trip_through_their_names() {
+        for tier in [Tier::Everyone, Tier::Moderator, Tier::Admin] {
+            assert_eq!(Tier::parse(tier.as_str()), Some(tier));
+        }
+    }
+
+    #[test]
+    fn unknown_tier_names_dont_parse() {
+        assert_eq!(Tier::parse("Admin"), None);
+        assert_eq!(Tier::parse("mod"), None);
+        assert_eq!(Tier::parse(""), None);
+    }
+
+    #[test]
+    fn higher_tiers_include_lower_ones() {
+        assert!(Tier::Admin > Tier::Moderator);
+        assert!(Tier::Moderator > Tier::Everyone);
+    }
+
+    #[test]
+    fn staff_commands_default_to_admin() {
+        assert_eq!(default_tier("recalculate"), Tier::Admin);
+        assert_eq!(default_tier("approval"), Tier::Admin);
+        assert_eq!(default_tier("drop"), Tier::Everyone);
+    }
+
+    #[test]
+    fn every_seeded_command_is_a_staff_command() {
+        let seed = include_str!("../../../migrations/20261017180000_command_permissions.sql");
+        let seeded: Vec<&str> = seed.lines()
+            .filter_map(|line| line.trim().strip_prefix("('"))
+            .filter_map(|line| line.split('\'').next())
+            .collect();
+        assert_eq!(seeded.len(), STAFF_COMMANDS.len());
+        for command in seeded {
+            assert!(STAFF_COMMANDS.contains(&command), "{} is seeded but not in STAFF_COMMANDS", command);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{format_gp, format_number};
+use crate::scoring::{PointRule, PointRules};
+use crate::logger;
+
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
+    Ok(())
+}
+
+fn describe(rule: &PointRule) -> String {
+    format!(
+        "1 point per {}, minimum {}{}",
+        format_gp(rule.gp_per_point),
+        format_gp(rule.min_value),
+        rule.max_points.map(|max| format!(", at most {} points", format_number(max))).unwrap_or_default()
+    )
+}
+
+pub async fn handle_point_rules(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let item_name = options
+        .iter()
+        .find(|opt| opt.name == "item")
+        .and_then(|opt| opt.value.as_str())
+        .map(|name| name.trim());
+    let gp_per_point = options
+        .iter()
+        .find(|opt| opt.name == "gp_per_point")
+        .and_then(|opt| opt.value.as_i64());
+    let min_value = options
+        .iter()
+        .find(|opt| opt.name == "min_value")
+        .and_then(|opt| opt.value.as_i64());
+    let max_points = options
+        .iter()
+        .find(|opt| opt.name == "max_points")
+        .and_then(|opt| opt.value.as_i64());
+    let clear = options
+        .iter()
+        .find(|opt| opt.name == "clear")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    let changing = gp_per_point.is_some() || min_value.is_some() || max_points.is_some();
+    let commanding_officer_id = command.user.id.to_string();
+
+    let details = match (item_name, clear) {
+        (Some(item_name), true) => {
+            let result = sqlx::query!("DELETE FROM point_rules WHERE item_name = ? AND item_name != '*'", item_name)
+                .execute(db)
+                .await?;
+            if result.rows_affected() == 0 {
+                return respond(command, ctx, format!("**{}** doesn't have its own point rule.", item_name)).await;
+            }
+            format!("{} now uses the default point rule", item_name)
+        }
+        (None, true) => {
+            return respond(command, ctx, "`clear` needs an `item` - the default rule can't be removed, only changed.".to_string()).await;
+        }
+        (_, false) if !changing => return list_point_rules(command, ctx, db).await,
+        (item_name, false) => {
+            // max_points 0 means "no cap", since a rule that awards nothing is just a higher min_value.
+            // It's stored as 0 rather than NULL, which on an item means "same as the default" - so an
+            // item can be uncapped even when the default has a cap
+            let key = item_name.unwrap_or("*");
+            let set_max_points = max_points.is_some();
+            sqlx::query!(
+                "INSERT INTO point_rules (item_name, gp_per_point, min_value, max_points) VALUES (?, ?, ?, ?)
+                 ON CONFLICT(item_name) DO UPDATE SET
+                     gp_per_point = COALESCE(excluded.gp_per_point, gp_per_point),
+                     min_value = COALESCE(excluded.min_value, min_value),
+                     max_points = CASE WHEN ? THEN excluded.max_points ELSE max_points END",
+                key,
+                gp_per_point,
+                min_value,
+                max_points,
+                set_max_points
+            )
+            .execute(db)
+            .await?;
+
+            let rules = PointRules::load(db).await?;
+            match item_name {
+                Some(item_name) => format!("{}: {}", item_name, describe(&rules.for_item(item_name))),
+                None => format!("Default: {}", describe(&rules.default)),
+            }
+        }
+    };
+
+    logger::log_action(ctx, &commanding_officer_id, "CHANGED POINT RULES", &details).await?;
+    respond(command, ctx, format!(
+        "Updated point rules. {}.\nThis only applies to new drops - run `/recalculate drops:True` to apply it to drops already recorded.",
+        details
+    )).await
+}
+
+async fn list_point_rules(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let rules = PointRules::load(db).await?;
+
+    let mut overrides: Vec<_> = rules.overrides.values().collect();
+    overrides.sort_by(|a, b| a.item_name.cmp(&b.item_name));
+    let override_list = if overrides.is_empty() {
+        "None".to_string()
+    } else {
+        overrides.iter()
+            .map(|item| format!("• **{}**: {}", item.item_name, describe(&rules.for_item(&item.item_name))))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Point Rules")
+        .color(0xffd700)
+        .field("Default", describe(&rules.default), false)
+        .field("Item Overrides", override_list, false)
+        .footer(CreateEmbedFooter::new("Change with /point_rules [item] [gp_per_point] [min_value] [max_points]"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{PriceManagerKey, format_gp, format_number};
+use crate::prices::TWAP_HOURS;
+
+fn gp_or_none(price: Option<f64>) -> String {
+    price.map(|price| format_gp(price.round() as i64)).unwrap_or_else(|| "no trades".to_string())
+}
+
+pub async fn handle_price_audit(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let drop_id = command.data.options
+        .iter()
+        .find(|opt| opt.name == "drop_id")
+        .and_then(|opt| opt.value.as_i64())
+        .ok_or_else(|| anyhow::anyhow!("Drop ID not provided"))?;
+
+    let drop = sqlx::query!(
+        r#"SELECT id as "id!", discord_id, item_name, item_id, quantity, value, timestamp, price_snapshot_id
+           FROM drops WHERE id = ?"#,
+        drop_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(drop) = drop else {
+        command
+            .create_response(&ctx.http, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(format!("There's no drop #{}.", drop_id))
+            ))
+            .await?;
+        return Ok(());
+    };
+
+    let price_each = drop.value / drop.quantity.max(1);
+    let mut embed = CreateEmbed::new()
+        .title(format!("Price Audit: Drop #{}", drop.id))
+        .color(0xffa500)
+        .field("Drop", format!(
+            "{}x {} by <@{}>{}\nRecorded at **{}** ({} each)",
+            format_number(drop.quantity),
+            drop.item_name,
+            drop.discord_id,
+            drop.timestamp.map(|at| format!(" on <t:{}:f>", at.and_utc().timestamp())).unwrap_or_default(),
+            format_gp(drop.value),
+            format_gp(price_each)
+        ), false);
+
+    // Everything that went into the average: the snapshot the drop points at and the ones before it
+    // in the same window
+    let snapshot = match drop.price_snapshot_id {
+        Some(snapshot_id) => sqlx::query!(
+            r#"SELECT id as "id!", avg_high, avg_low, high_volume, low_volume, taken_at FROM price_snapshots WHERE id = ?"#,
+            snapshot_id
+        )
+        .fetch_optional(db)
+        .await?,
+        None => None,
+    };
+
+    match snapshot {
+        Some(snapshot) => {
+            let window = format!("-{} hours", TWAP_HOURS);
+            let averaged = sqlx::query!(
+                r#"SELECT COUNT(*) as "count!: i64",
+                          AVG(COALESCE(avg_low, avg_high)) as "average: f64",
+                          MIN(COALESCE(avg_low, avg_high)) as "lowest: f64",
+                          MAX(COALESCE(avg_low, avg_high)) as "highest: f64"
+                   FROM price_snapshots
+                   WHERE item_id = ? AND id <= ? AND taken_at >= datetime(?, ?)
+                   AND COALESCE(avg_low, avg_high) IS NOT NULL"#,
+                drop.item_id,
+                snapshot.id,
+                drop.timestamp,
+                window
+            )
+            .fetch_one(db)
+            .await?;
+
+            embed = embed
+                .field(format!("{}-hour Average Used", TWAP_HOURS), format!(
+                    "**{}** over {} hourly snapshots (lowest {}, highest {})",
+                    gp_or_none(averaged.average),
+                    averaged.count,
+                    gp_or_none(averaged.lowest),
+                    gp_or_none(averaged.highest)
+                ), false)
+                .field(format!("Latest Snapshot (#{}, <t:{}:f>)", snapshot.id, snapshot.taken_at.and_utc().timestamp()), format!(
+                    "Sell: {} ({} traded)\nBuy: {} ({} traded)",
+                    gp_or_none(snapshot.avg_low),
+                    format_number(snapshot.low_volume),
+                    gp_or_none(snapshot.avg_high),
+                    format_number(snapshot.high_volume)
+                ), false);
+        }
+        None => {
+            embed = embed.field(
+                "Price Used",
+                "No snapshot - the drop was valued with a price override, from the latest price (before any snapshots were taken), or came from the RuneLite tracker, which sends its own value",
+                false
+            );
+        }
+    }
+
+    let data = ctx.data.read().await;
+    let current_price = match (data.get::<PriceManagerKey>(), drop.item_id) {
+        (Some(price_manager), Some(item_id)) => price_manager.get_drop_price(item_id).await,
+        _ => None,
+    };
+    if let Some(current_price) = current_price {
+        let note = if current_price.overridden {
+            "\nSet by hand with /price_override".to_string()
+        } else if let Some(flag) = &current_price.flag {
+            format!("\n⚠️ Market price not trusted ({}): the market says {} each", flag.reason, format_gp(flag.market_price))
+        } else {
+            String::new()
+        };
+        embed = embed.field("Worth Now", format!(
+            "{} each, {} for the drop{}",
+            format_gp(current_price.price_each),
+            format_gp(current_price.price_each * drop.quantity),
+            note
+        ), false);
+    }
+
+    embed = embed.footer(CreateEmbedFooter::new("Prices are hourly averages from prices.runescape.wiki"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{PriceManagerKey, format_gp};
+use crate::logger;
+
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_price_override(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let typed_name = options
+        .iter()
+        .find(|opt| opt.name == "item")
+        .and_then(|opt| opt.value.as_str())
+        .map(|name| name.trim());
+    let price = options
+        .iter()
+        .find(|opt| opt.name == "price")
+        .and_then(|opt| opt.value.as_i64());
+    let clear = options
+        .iter()
+        .find(|opt| opt.name == "clear")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    let Some(typed_name) = typed_name else {
+        return list_price_overrides(command, ctx, db).await;
+    };
+
+    let item = {
+        let data = ctx.data.read().await;
+        let price_manager = data.get::<PriceManagerKey>()
+            .ok_or_else(|| anyhow::anyhow!("Price manager not found"))?;
+        match price_manager.resolve_item_name(typed_name).await {
+            Some(item_name) => price_manager.get_item_id(&item_name).await.map(|item_id| (item_id, item_name)),
+            None => None,
+        }
+    };
+    let Some((item_id, item_name)) = item else {
+        return respond(command, ctx, format!("Item '{}' not found in price database.", typed_name)).await;
+    };
+
+    let commanding_officer_id = command.user.id.to_string();
+    let details = match (price, clear) {
+        (_, true) => {
+            let result = sqlx::query!("DELETE FROM price_overrides WHERE item_id = ?", item_id)
+                .execute(db)
+                .await?;
+            if result.rows_affected() == 0 {
+                return respond(command, ctx, format!("**{}** doesn't have a price override.", item_name)).await;
+            }
+            format!("{} is valued at its market price again", item_name)
+        }
+        (Some(price), false) => {
+            sqlx::query!(
+                "INSERT INTO price_overrides (item_id, item_name, price, set_by) VALUES (?, ?, ?, ?)
+                 ON CONFLICT(item_id) DO UPDATE SET
+                     item_name = excluded.item_name,
+                     price = excluded.price,
+                     set_by = excluded.set_by,
+                     set_at = CURRENT_TIMESTAMP",
+                item_id,
+                item_name,
+                price,
+                commanding_officer_id
+            )
+            .execute(db)
+            .await?;
+            format!("{} is now valued at {} each", item_name, format_gp(price))
+        }
+        (None, false) => {
+            return respond(command, ctx, "Give a `price` to set, or `clear:True` to remove the override.".to_string()).await;
+        }
+    };
+
+    logger::log_action(ctx, &commanding_officer_id, "CHANGED PRICE OVERRIDE", &details).await?;
+    respond(command, ctx, format!(
+        "Updated price overrides. {}.\nThis only applies to new drops - drops already recorded keep their value.",
+        details
+    )).await
+}
+
+async fn list_price_overrides(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let overrides = sqlx::query!(
+        "SELECT item_name, price, set_by, set_at FROM price_overrides ORDER BY item_name"
+    )
+    .fetch_all(db)
+    .await?;
+
+    let override_list = if overrides.is_empty() {
+        "None".to_string()
+    } else {
+        overrides.iter()
+            .map(|item| format!(
+                "• **{}**: {} (set by <@{}> <t:{}:R>)",
+                item.item_name,
+                format_gp(item.price),
+                item.set_by,
+                item.set_at.and_utc().timestamp()
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Price Overrides")
+        .color(0xffd700)
+        .description(override_list)
+        .footer(CreateEmbedFooter::new("Change with /price_override <item> [price] [clear]"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
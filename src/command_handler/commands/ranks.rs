@@ -0,0 +1,293 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction,
+    CreateEmbed,
+    CreateEmbedFooter,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::format_number;
+use crate::rank_manager;
+use crate::logger;
+
+// Every rank command answers with a plain message, so keep the boilerplate in one place
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
+    Ok(())
+}
+
+// Changing thresholds can shuffle everyone's rank around, which means a role update per user -
+// that can take a while, so acknowledge first and report back when it's done
+async fn reevaluate_and_report(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+    old_thresholds: &[(i64, String)],
+    summary: String,
+) -> Result<()> {
+    respond(command, ctx, format!("{}\nRe-evaluating everyone's rank...", summary)).await?;
+
+    let changed = rank_manager::reevaluate_ranks(ctx, old_thresholds, db).await?;
+    let result = if changed == 0 {
+        "Nobody's rank changed.".to_string()
+    } else {
+        format!("{} users changed rank, see the mod channel for details.", format_number(changed as i64))
+    };
+
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(format!("{}\n{}", summary, result)))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_rank_list(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let ranks = sqlx::query!(
+        "SELECT points, role_name, unlocks_perks, announcement FROM rank_thresholds ORDER BY points ASC"
+    )
+    .fetch_all(db)
+    .await?;
+
+    let list = if ranks.is_empty() {
+        "No ranks set up yet! Add one with `/rank_add`.".to_string()
+    } else {
+        ranks.iter()
+            .map(|rank| {
+                let perks = if rank.unlocks_perks != 0 {
+                    format!(" ⭐ *{}*", rank.announcement.as_deref().unwrap_or(rank_manager::DEFAULT_PERK_ANNOUNCEMENT))
+                } else {
+                    String::new()
+                };
+                format!("• **{}**: {} points{}", rank.role_name, format_number(rank.points), perks)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Rank Thresholds")
+        .color(0xffd700)
+        .description(list)
+        .footer(CreateEmbedFooter::new("⭐ = unlocks perks, announced when reached"));
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(embed)
+        ))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_rank_add(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let role_name = options
+        .iter()
+        .find(|opt| opt.name == "name")
+        .and_then(|opt| opt.value.as_str())
+        .map(|name| name.trim())
+        .ok_or_else(|| anyhow::anyhow!("Rank name not provided"))?;
+    let points = options
+        .iter()
+        .find(|opt| opt.name == "points")
+        .and_then(|opt| opt.value.as_i64())
+        .ok_or_else(|| anyhow::anyhow!("Rank points not provided"))?;
+    let unlocks_perks = options
+        .iter()
+        .find(|opt| opt.name == "perks")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+    let announcement = options
+        .iter()
+        .find(|opt| opt.name == "announcement")
+        .and_then(|opt| opt.value.as_str());
+
+    let old_thresholds = rank_manager::rank_thresholds(db).await?;
+
+    if let Some((_, existing)) = old_thresholds.iter().find(|(p, _)| *p == points) {
+        return respond(command, ctx, format!("**{}** already sits at {} points. Pick a different threshold or use `/rank_edit`.", existing, format_number(points))).await;
+    }
+    if old_thresholds.iter().any(|(_, name)| name == role_name) {
+        return respond(command, ctx, format!("There's already a rank called **{}**.", role_name)).await;
+    }
+
+    sqlx::query!(
+        "INSERT INTO rank_thresholds (points, role_name, unlocks_perks, announcement) VALUES (?, ?, ?, ?)",
+        points,
+        role_name,
+        unlocks_perks,
+        announcement
+    )
+    .execute(db)
+    .await?;
+
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "ADDED RANK", &format!("{} at {} points", role_name, format_number(points))).await?;
+
+    reevaluate_and_report(command, ctx, db, &old_thresholds,
+        format!("Added the **{}** rank at {} points.", role_name, format_number(points))).await
+}
+
+pub async fn handle_rank_remove(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let role_name = command.data.options
+        .iter()
+        .find(|opt| opt.name == "rank")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Rank name not provided"))?;
+
+    let old_thresholds = rank_manager::rank_thresholds(db).await?;
+
+    let result = sqlx::query!("DELETE FROM rank_thresholds WHERE role_name = ?", role_name)
+        .execute(db)
+        .await?;
+    if result.rows_affected() == 0 {
+        return respond(command, ctx, format!("There's no rank called **{}**.", role_name)).await;
+    }
+
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "REMOVED RANK", role_name).await?;
+
+    reevaluate_and_report(command, ctx, db, &old_thresholds,
+        format!("Removed the **{}** rank.", role_name)).await
+}
+
+pub async fn handle_rank_edit(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let options = &command.data.options;
+    let role_name = options
+        .iter()
+        .find(|opt| opt.name == "rank")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Rank name not provided"))?;
+    let new_name = options
+        .iter()
+        .find(|opt| opt.name == "name")
+        .and_then(|opt| opt.value.as_str())
+        .map(|name| name.trim());
+    let new_points = options
+        .iter()
+        .find(|opt| opt.name == "points")
+        .and_then(|opt| opt.value.as_i64());
+    let unlocks_perks = options
+        .iter()
+        .find(|opt| opt.name == "perks")
+        .and_then(|opt| opt.value.as_bool());
+    let announcement = options
+        .iter()
+        .find(|opt| opt.name == "announcement")
+        .and_then(|opt| opt.value.as_str());
+
+    if new_name.is_none() && new_points.is_none() && unlocks_perks.is_none() && announcement.is_none() {
+        return respond(command, ctx, "Nothing to change! Give the rank a new `name`, `points`, `perks` or `announcement`.".to_string()).await;
+    }
+
+    // Perks only change what gets announced on the way up, not who has which rank, so there's
+    // nothing to re-evaluate if that's all that changed
+    if new_name.is_none() && new_points.is_none() {
+        let result = sqlx::query!(
+            "UPDATE rank_thresholds
+             SET unlocks_perks = COALESCE(?, unlocks_perks), announcement = COALESCE(?, announcement)
+             WHERE role_name = ?",
+            unlocks_perks,
+            announcement,
+            role_name
+        )
+        .execute(db)
+        .await?;
+        if result.rows_affected() == 0 {
+            return respond(command, ctx, format!("There's no rank called **{}**.", role_name)).await;
+        }
+
+        let details = format!(
+            "{}: perks {}{}",
+            role_name,
+            match unlocks_perks {
+                Some(true) => "on",
+                Some(false) => "off",
+                None => "unchanged",
+            },
+            announcement.map(|text| format!(", announcement \"{}\"", text)).unwrap_or_default()
+        );
+        let commanding_officer_id = command.user.id.to_string();
+        logger::log_action(ctx, &commanding_officer_id, "EDITED RANK", &details).await?;
+        return respond(command, ctx, format!("Updated rank: {}.", details)).await;
+    }
+
+    let old_thresholds = rank_manager::rank_thresholds(db).await?;
+    let Some(position) = old_thresholds.iter().position(|(_, name)| name == role_name) else {
+        return respond(command, ctx, format!("There's no rank called **{}**.", role_name)).await;
+    };
+    let old_points = old_thresholds[position].0;
+    let new_name = new_name.unwrap_or(role_name);
+    let new_points = new_points.unwrap_or(old_points);
+
+    if new_name != role_name && old_thresholds.iter().any(|(_, name)| name == new_name) {
+        return respond(command, ctx, format!("There's already a rank called **{}**.", new_name)).await;
+    }
+
+    // Moving a threshold past its neighbours would silently reorder the ladder (and swap which
+    // rank counts as a promotion), so edits have to stay between the ranks either side of it.
+    // Reordering is a remove + add.
+    let lower = position.checked_sub(1).map(|i| &old_thresholds[i]);
+    let upper = old_thresholds.get(position + 1);
+    if let Some((lower_points, lower_name)) = lower {
+        if new_points <= *lower_points {
+            return respond(command, ctx, format!(
+                "**{}** has to stay above **{}** ({} points). To reorder ranks, remove and re-add them.",
+                role_name, lower_name, format_number(*lower_points)
+            )).await;
+        }
+    }
+    if let Some((upper_points, upper_name)) = upper {
+        if new_points >= *upper_points {
+            return respond(command, ctx, format!(
+                "**{}** has to stay below **{}** ({} points). To reorder ranks, remove and re-add them.",
+                role_name, upper_name, format_number(*upper_points)
+            )).await;
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE rank_thresholds
+         SET points = ?, role_name = ?, unlocks_perks = COALESCE(?, unlocks_perks), announcement = COALESCE(?, announcement)
+         WHERE role_name = ?",
+        new_points,
+        new_name,
+        unlocks_perks,
+        announcement,
+        role_name
+    )
+    .execute(db)
+    .await?;
+
+    let details = format!(
+        "{} ({} points) -> {} ({} points)",
+        role_name,
+        format_number(old_points),
+        new_name,
+        format_number(new_points)
+    );
+    let commanding_officer_id = command.user.id.to_string();
+    logger::log_action(ctx, &commanding_officer_id, "EDITED RANK", &details).await?;
+
+    reevaluate_and_report(command, ctx, db, &old_thresholds, format!("Updated rank: {}.", details)).await
+}
@@ -8,6 +8,10 @@ use serenity::all::{
 use sqlx::SqlitePool;
 use crate::command_handler::{format_points, format_number, format_gp};
 
+fn screenshot_link(evidence_url: Option<&str>) -> String {
+    evidence_url.map(|url| format!(" [📷]({})", url)).unwrap_or_default()
+}
+
 pub async fn handle_stats(
     command: &CommandInteraction,
     ctx: &serenity::prelude::Context,
@@ -27,7 +31,7 @@ pub async fn handle_stats(
         Some(data) => {
             // Get collection log count
             let clog_count = sqlx::query!(
-                "SELECT COUNT(*) as count FROM collection_log_entries WHERE discord_id = ?",
+                "SELECT COUNT(*) as count FROM collection_log_entries WHERE discord_id = ? AND deleted_at IS NULL",
                 discord_id
             )
             .fetch_one(db)
@@ -76,9 +80,9 @@ pub async fn handle_stats(
 
             // Get 5 most recent drops
             let recent_drops = sqlx::query!(
-                "SELECT item_name, quantity, value, timestamp 
+                "SELECT item_name, quantity, value, timestamp, evidence_url 
                  FROM drops 
-                 WHERE discord_id = ? 
+                 WHERE discord_id = ? AND deleted_at IS NULL
                  ORDER BY timestamp DESC 
                  LIMIT 5",
                 discord_id
@@ -88,9 +92,9 @@ pub async fn handle_stats(
 
             // Get 5 most recent collection log entries
             let recent_clogs = sqlx::query!(
-                "SELECT item_name, points, timestamp 
+                "SELECT item_name, points, timestamp, evidence_url 
                  FROM collection_log_entries 
-                 WHERE discord_id = ? 
+                 WHERE discord_id = ? AND deleted_at IS NULL
                  ORDER BY timestamp DESC 
                  LIMIT 5",
                 discord_id
@@ -98,23 +102,36 @@ pub async fn handle_stats(
             .fetch_all(db)
             .await?;
 
-            // Get most valuable drop
+            // Get most valuable drop, under the item's current name
             let most_valuable_drop = sqlx::query!(
-                "SELECT item_name, quantity, value 
-                 FROM drops 
-                 WHERE discord_id = ? 
-                 ORDER BY value DESC 
-                 LIMIT 1",
+                r#"SELECT COALESCE(n.item_name, d.item_name) as "item_name!: String", d.quantity, d.value 
+                 FROM drops d
+                 LEFT JOIN v_drop_item_names n ON n.item_id = d.item_id
+                 WHERE d.discord_id = ? AND d.deleted_at IS NULL
+                 ORDER BY d.value DESC 
+                 LIMIT 1"#,
                 discord_id
             )
             .fetch_optional(db)
             .await?;
 
+            // Get the items that have been worth the most in total, by item id so renamed items add up
+            let top_items = sqlx::query!(
+                r#"SELECT item_name as "item_name!: String", drop_count as "drop_count!: i64", total_value as "total_value!: i64"
+                 FROM v_drop_totals
+                 WHERE discord_id = ?
+                 ORDER BY total_value DESC
+                 LIMIT 3"#,
+                discord_id
+            )
+            .fetch_all(db)
+            .await?;
+
             // Get rarest collection log entry
             let rarest_clog = sqlx::query!(
                 "SELECT item_name, points 
                  FROM collection_log_entries 
-                 WHERE discord_id = ? 
+                 WHERE discord_id = ? AND deleted_at IS NULL
                  ORDER BY points DESC 
                  LIMIT 1",
                 discord_id
@@ -126,7 +143,7 @@ pub async fn handle_stats(
             let avg_drop_value = sqlx::query!(
                 "SELECT AVG(CAST(value AS FLOAT)) as avg_value 
                  FROM drops 
-                 WHERE discord_id = ?",
+                 WHERE discord_id = ? AND deleted_at IS NULL",
                 discord_id
             )
             .fetch_one(db)
@@ -138,7 +155,7 @@ pub async fn handle_stats(
             let avg_clog_points = sqlx::query!(
                 "SELECT AVG(CAST(points AS FLOAT)) as avg_points 
                  FROM collection_log_entries 
-                 WHERE discord_id = ?",
+                 WHERE discord_id = ? AND deleted_at IS NULL",
                 discord_id
             )
             .fetch_one(db)
@@ -162,10 +179,11 @@ pub async fn handle_stats(
                     .iter()
                     .map(|drop| {
                         format!(
-                            "• {}x {} ({})",
+                            "• {}x {} ({}){}",
                             format_number(drop.quantity),
                             drop.item_name,
-                            format_gp(drop.value)
+                            format_gp(drop.value),
+                            screenshot_link(drop.evidence_url.as_deref())
                         )
                     })
                     .collect::<Vec<_>>()
@@ -180,9 +198,10 @@ pub async fn handle_stats(
                     .iter()
                     .map(|clog| {
                         format!(
-                            "• {} (+{} pts)",
+                            "• {} (+{} pts){}",
                             clog.item_name,
-                            format_number(clog.points)
+                            format_number(clog.points),
+                            screenshot_link(clog.evidence_url.as_deref())
                         )
                     })
                     .collect::<Vec<_>>()
@@ -201,6 +220,24 @@ pub async fn handle_stats(
                 })
                 .unwrap_or_else(|| "No drops recorded yet".to_string());
 
+            // Format top items
+            let top_items_text = if top_items.is_empty() {
+                "No drops recorded yet".to_string()
+            } else {
+                top_items
+                    .iter()
+                    .map(|item| {
+                        format!(
+                            "• {} ({} from {} drops)",
+                            item.item_name,
+                            format_gp(item.total_value),
+                            format_number(item.drop_count)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
             // Format rarest collection log entry
             let rarest_clog_text = rarest_clog
                 .map(|clog| {
@@ -228,7 +265,8 @@ pub async fn handle_stats(
                 .field("Recent Drops", recent_drops_text, false)
                 .field("Recent Collection Log", recent_clogs_text, false)
                 .field("Most Valuable Drop", most_valuable_text, true)
-                .field("Rarest Collection Log Entry", rarest_clog_text, true);
+                .field("Rarest Collection Log Entry", rarest_clog_text, true)
+                .field("Top Items", top_items_text, false);
 
             command
                 .create_response(&ctx.http, CreateInteractionResponse::Message(
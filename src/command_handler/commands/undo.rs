@@ -0,0 +1,245 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use serenity::all::{
+    CommandInteraction,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+    UserId,
+};
+use sqlx::SqlitePool;
+use crate::command_handler::{format_gp, format_number, format_points};
+use crate::rank_manager;
+use crate::logger;
+
+// How long after a removal /undo can still bring it back
+pub const UNDO_WINDOW_MINUTES: i64 = 15;
+
+/// Tacked onto the reply to a removal
+pub fn undo_hint() -> String {
+    format!("Removed by mistake? `/undo` brings it back for the next {} minutes.", UNDO_WINDOW_MINUTES)
+}
+
+async fn respond(command: &CommandInteraction, ctx: &serenity::prelude::Context, content: String) -> Result<()> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content)
+        ))
+        .await?;
+    Ok(())
+}
+
+// The name rank announcements use for whoever the entry belongs to - a mod may be undoing their
+// removal of someone else's
+async fn owner_name(command: &CommandInteraction, ctx: &serenity::prelude::Context, discord_id: &str) -> String {
+    if command.user.id.to_string() == discord_id {
+        return command.member.as_ref()
+            .map(|m| m.display_name().to_string())
+            .unwrap_or_else(|| command.user.name.clone());
+    }
+    match discord_id.parse::<u64>() {
+        Ok(user_id) => match ctx.http.get_user(UserId::new(user_id)).await {
+            Ok(user) => user.display_name().to_string(),
+            Err(_) => discord_id.to_string(),
+        },
+        Err(_) => discord_id.to_string(),
+    }
+}
+
+// "you" or a mention, for the reply
+fn whose(command: &CommandInteraction, discord_id: &str) -> String {
+    if command.user.id.to_string() == discord_id {
+        "your".to_string()
+    } else {
+        format!("<@{}>'s", discord_id)
+    }
+}
+
+fn points_text(points: i64, points_update: Option<rank_manager::PointsUpdate>) -> String {
+    match points_update {
+        Some(points_update) => format!(
+            "{} points have been added back, for a total of {}.",
+            format_number(points),
+            format_points(points_update.new_points)
+        ),
+        None => "It wasn't worth any points.".to_string(),
+    }
+}
+
+/// Brings back the last drop or collection log entry the user removed (with /drop_remove,
+/// /clog_remove or the admin versions), as long as it was within the last UNDO_WINDOW_MINUTES
+pub async fn handle_undo(
+    command: &CommandInteraction,
+    ctx: &serenity::prelude::Context,
+    db: &SqlitePool,
+) -> Result<()> {
+    let removed_by = command.user.id.to_string();
+    let window = format!("-{} minutes", UNDO_WINDOW_MINUTES);
+
+    let drop = sqlx::query!(
+        r#"SELECT id, discord_id, item_name, value, quantity, points, deleted_at as "deleted_at!: NaiveDateTime"
+           FROM drops
+           WHERE deleted_by = ? AND deleted_at >= datetime('now', ?)
+           ORDER BY deleted_at DESC, id DESC
+           LIMIT 1"#,
+        removed_by,
+        window
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let clog = sqlx::query!(
+        r#"SELECT id, discord_id, item_name, item_id, points, deleted_at as "deleted_at!: NaiveDateTime"
+           FROM collection_log_entries
+           WHERE deleted_by = ? AND deleted_at >= datetime('now', ?)
+           ORDER BY deleted_at DESC, id DESC
+           LIMIT 1"#,
+        removed_by,
+        window
+    )
+    .fetch_optional(db)
+    .await?;
+
+    // Whichever of the two was removed last
+    let restore_clog = match (&drop, &clog) {
+        (Some(drop), Some(clog)) => clog.deleted_at > drop.deleted_at,
+        (None, Some(_)) => true,
+        _ => false,
+    };
+
+    if restore_clog {
+        let Some(entry) = clog else {
+            return Ok(());
+        };
+
+        // They may have logged it again since (or Dink did)
+        let relogged = sqlx::query_scalar!(
+            r#"SELECT id as "id!" FROM collection_log_entries WHERE discord_id = ? AND item_id = ? AND deleted_at IS NULL"#,
+            entry.discord_id,
+            entry.item_id
+        )
+        .fetch_optional(db)
+        .await?;
+        if let Some(relogged) = relogged {
+            return respond(command, ctx, format!(
+                "Can't bring back {} collection log entry for {} - it's been logged again since (ID {}).",
+                whose(command, &entry.discord_id),
+                entry.item_name,
+                relogged
+            )).await;
+        }
+
+        // The entry and its points come back together
+        let mut tx = db.begin().await?;
+        let restored = sqlx::query!(
+            "UPDATE collection_log_entries SET deleted_at = NULL, deleted_by = NULL, delete_reason = NULL
+             WHERE id = ? AND deleted_at IS NOT NULL",
+            entry.id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if restored == 0 {
+            return respond(command, ctx, "That's already been undone.".to_string()).await;
+        }
+        let totals = if entry.points > 0 {
+            Some(rank_manager::record_points(
+                &mut tx,
+                &entry.discord_id,
+                entry.points,
+                "clog_restored",
+                Some(&format!("clog #{}", entry.id))
+            ).await?)
+        } else {
+            None
+        };
+        tx.commit().await?;
+
+        logger::log_action(
+            ctx,
+            &entry.discord_id,
+            "RESTORED CLOG",
+            &format!("{} ({} pts) [ID: {}] by <@{}>", entry.item_name, entry.points, entry.id, removed_by)
+        ).await?;
+
+        let points_update = match totals {
+            Some((old_points, new_points)) => {
+                let user_name = owner_name(command, ctx, &entry.discord_id).await;
+                Some(rank_manager::finish_points_update(ctx, &entry.discord_id, &user_name, old_points, new_points, db).await?)
+            }
+            None => None,
+        };
+
+        return respond(command, ctx, format!(
+            "Restored {} collection log entry: {}. {}",
+            whose(command, &entry.discord_id),
+            entry.item_name,
+            points_text(entry.points, points_update)
+        )).await;
+    }
+
+    let Some(drop) = drop else {
+        return respond(command, ctx, format!(
+            "You haven't removed any drops or collection log entries in the last {} minutes.",
+            UNDO_WINDOW_MINUTES
+        )).await;
+    };
+
+    // The drop, its total_drops and its points come back together
+    let mut tx = db.begin().await?;
+    let restored = sqlx::query!(
+        "UPDATE drops SET deleted_at = NULL, deleted_by = NULL, delete_reason = NULL
+         WHERE id = ? AND deleted_at IS NOT NULL",
+        drop.id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if restored == 0 {
+        return respond(command, ctx, "That's already been undone.".to_string()).await;
+    }
+
+    // Give back what /drop_remove took off their total_drops
+    sqlx::query!(
+        "UPDATE users SET total_drops = total_drops + ? WHERE discord_id = ?",
+        drop.quantity,
+        drop.discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let totals = if drop.points > 0 {
+        Some(rank_manager::record_points(
+            &mut tx,
+            &drop.discord_id,
+            drop.points,
+            "drop_restored",
+            Some(&format!("drop #{}", drop.id))
+        ).await?)
+    } else {
+        None
+    };
+    tx.commit().await?;
+
+    logger::log_action(
+        ctx,
+        &drop.discord_id,
+        "RESTORED DROP",
+        &format!("{}x {} ({}) [ID: {}] by <@{}>", drop.quantity, drop.item_name, format_gp(drop.value), drop.id, removed_by)
+    ).await?;
+
+    let points_update = match totals {
+        Some((old_points, new_points)) => {
+            let user_name = owner_name(command, ctx, &drop.discord_id).await;
+            Some(rank_manager::finish_points_update(ctx, &drop.discord_id, &user_name, old_points, new_points, db).await?)
+        }
+        None => None,
+    };
+
+    respond(command, ctx, format!(
+        "Restored {} drop: {}x {} ({}). {}",
+        whose(command, &drop.discord_id),
+        format_number(drop.quantity),
+        drop.item_name,
+        format_gp(drop.value),
+        points_text(drop.points, points_update)
+    )).await
+}
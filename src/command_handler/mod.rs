@@ -1,5 +1,5 @@
 use anyhow::Result;use serenity::all::{
-    AutocompleteChoice, Command, CommandOptionType, CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse, Interaction, Permissions
+    AutocompleteChoice, Command, CommandOptionType, CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, Interaction
 };
 use serenity::prelude::*;
 use sqlx::SqlitePool;
@@ -30,7 +30,13 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
             "The quantity of items (default: 1)"
         )
         .required(false)
-        .min_int_value(1)))
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Attachment,
+            "screenshot",
+            "A screenshot of the drop"
+        )
+        .required(false)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("drop_remove")
@@ -42,7 +48,17 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
         )
         .required(false)
         .set_autocomplete(true)
-        .min_int_value(1)))
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "reason",
+            "Why it's being removed (kept with the removed entry)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("undo")
+        .description(format!("Bring back the last drop or clog you removed (within {} minutes)", UNDO_WINDOW_MINUTES)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("clog")
@@ -53,7 +69,13 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
             "The name of the collection log item"
         )
         .required(true)
-        .set_autocomplete(true)))
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Attachment,
+            "screenshot",
+            "A screenshot of the collection log"
+        )
+        .required(false)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("clog_remove")
@@ -65,7 +87,13 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
         )
         .required(false)
         .set_autocomplete(true)
-        .min_int_value(1)))
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "reason",
+            "Why it's being removed (kept with the removed entry)"
+        )
+        .required(false)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("points")
@@ -79,6 +107,34 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
     Command::create_global_command(&ctx.http, CreateCommand::new("stats")
         .description("View detailed statistics for your account"))
     .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("kc")
+        .description("View kill count and personal best at a boss")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "boss",
+            "The name of the boss"
+        )
+        .required(true)
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Whose kill count to show (default: yours)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("pb_board")
+        .description("View the clan's fastest personal bests at a boss")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "boss",
+            "The name of the boss"
+        )
+        .required(true)
+        .set_autocomplete(true)))
+    .await?;
         
     Command::create_global_command(&ctx.http, CreateCommand::new("rsname")
         .description("Link a RuneScape username to your Discord account")
@@ -104,28 +160,176 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
         .description("List all RuneScape accounts linked to your Discord account"))
     .await?;
 
-    let admin_permission_set = Permissions::ADMINISTRATOR;
-
+    // Staff commands are visible to everyone - who can actually use them is down to
+    // command_permissions, checked in handle_interaction
     Command::create_global_command(&ctx.http, CreateCommand::new("recalculate")
         .description("ADMIN: Recalculate all points based on clamped categories.")
-        .default_member_permissions(admin_permission_set))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "drops",
+            "Reapply the current point rules to every recorded drop instead (default: clogs)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("point_rules")
+        .description("ADMIN: Set how many points drops are worth, for everything or one item, or list the rules.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "item",
+            "Only change this item (default: change the default rule)"
+        )
+        .required(false)
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "gp_per_point",
+            "GP value per point"
+        )
+        .required(false)
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "min_value",
+            "Drops worth less than this get no points"
+        )
+        .required(false)
+        .min_int_value(0))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "max_points",
+            "Most points a single drop can give (0 for no cap, even if the default has one)"
+        )
+        .required(false)
+        .min_int_value(0))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "clear",
+            "Remove the item's own rule so it uses the default again"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("price_audit")
+        .description("ADMIN: Show the prices a drop was valued at, to check a disputed value.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "drop_id",
+            "ID of the drop (shown in the bot log and /drop_remove)"
+        )
+        .required(true)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("price_override")
+        .description("ADMIN: Set the price new drops of an item are valued at, or list the overrides.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "item",
+            "The item to set a price for (leave empty to list the overrides)"
+        )
+        .required(false)
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "price",
+            "GP value per item"
+        )
+        .required(false)
+        .min_int_value(0))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "clear",
+            "Go back to the market price for this item"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("clog_curve")
+        .description("ADMIN: Show, preview or change the curve that turns clog rarity into points.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "rare_rate",
+            "Completion rate (%) at or below which an item is rare"
+        )
+        .required(false)
+        .min_number_value(0.01))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "rare_base",
+            "Base points for rare items"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "rare_multiplier",
+            "Multiplier for rare items"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "rare_exponent",
+            "How steeply rare items gain points as they get rarer"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "uncommon_rate",
+            "Completion rate (%) at or below which an item is uncommon"
+        )
+        .required(false)
+        .min_number_value(0.01))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "uncommon_min_points",
+            "Points for the most common uncommon item"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "uncommon_max_points",
+            "Points for the rarest uncommon item"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "common_base",
+            "Base points for common items"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Number,
+            "common_slope",
+            "Points common items lose per % of completion rate"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "apply",
+            "Save the new curve and rescore every clog already recorded (default: just preview)"
+        )
+        .required(false)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("clamp")
         .description("ADMIN: Clamp the points a category is allowed to give.")
-        .default_member_permissions(admin_permission_set)
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
             "category",
             "The name of the category"
         )
         .required(true)
-        .set_autocomplete(true)))
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "max",
+            "Most points an item in this category can give (default 3000)"
+        )
+        .required(false)
+        .min_int_value(1)))
     .await?;
 
     Command::create_global_command(&ctx.http, CreateCommand::new("unclamp")
         .description("ADMIN: Unclamp the points a category is allowed to give.")
-        .default_member_permissions(admin_permission_set)
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
             "category",
@@ -137,7 +341,6 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
 
     Command::create_global_command(&ctx.http, CreateCommand::new("whitelist")
         .description("ADMIN: Whitelist a clog item to never be clamped.")
-        .default_member_permissions(admin_permission_set)
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
             "item",
@@ -149,7 +352,6 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
 
     Command::create_global_command(&ctx.http, CreateCommand::new("unwhitelist")
         .description("ADMIN: Unwhitelist a clog item, so it will be clamped (if the category is clamped).")
-        .default_member_permissions(admin_permission_set)
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
             "item",
@@ -159,20 +361,291 @@ pub async fn register_commands(ctx: &Context) -> Result<()> {
         .set_autocomplete(true)))
     .await?;
 
+    Command::create_global_command(&ctx.http, CreateCommand::new("audit_points")
+        .description("ADMIN: List everyone whose points don't match their drops and clogs.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "fix",
+            "Correct their points to match (default: just list them)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("admin_drop_add")
+        .description("ADMIN: Record a drop for someone else.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Who got the drop"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "item",
+            "The name of the item"
+        )
+        .required(true)
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "quantity",
+            "The quantity of items (default: 1)"
+        )
+        .required(false)
+        .min_int_value(1)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("admin_drop_remove")
+        .description("ADMIN: Remove one of someone else's drops.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Whose drop to remove"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "id",
+            "The ID of the drop to remove"
+        )
+        .required(true)
+        .set_autocomplete(true)
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "reason",
+            "Why it's being removed (kept with the removed entry)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("admin_clog_add")
+        .description("ADMIN: Record a collection log item for someone else.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Who got the item"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "item",
+            "The name of the collection log item"
+        )
+        .required(true)
+        .set_autocomplete(true)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("admin_clog_remove")
+        .description("ADMIN: Remove one of someone else's collection log entries.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Whose entry to remove"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "id",
+            "The ID of the collection log entry to remove"
+        )
+        .required(true)
+        .set_autocomplete(true)
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "reason",
+            "Why it's being removed (kept with the removed entry)"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("admin_points_adjust")
+        .description("ADMIN: Give or take points that don't belong to a drop or clog.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            "Whose points to adjust"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "amount",
+            "Points to add (negative to take away)"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "reason",
+            "Why - kept in the points ledger and the log"
+        )
+        .required(true)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("rank_list")
+        .description("ADMIN: List every rank and the points needed for it."))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("rank_add")
+        .description("ADMIN: Add a new rank at a points threshold.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "name",
+            "The name of the rank (must match the Discord role name)"
+        )
+        .required(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "points",
+            "The points needed to reach this rank"
+        )
+        .required(true)
+        .min_int_value(0))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "perks",
+            "Whether reaching this rank unlocks perks (announced when reached)"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "announcement",
+            "Perk announcement text; {user} and {channel} mention the user and rank request channel"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("rank_remove")
+        .description("ADMIN: Remove a rank.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "rank",
+            "The name of the rank"
+        )
+        .required(true)
+        .set_autocomplete(true)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("rank_edit")
+        .description("ADMIN: Rename a rank, move its points threshold, or change its perks.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "rank",
+            "The name of the rank"
+        )
+        .required(true)
+        .set_autocomplete(true))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "name",
+            "The new name of the rank"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "points",
+            "The new points needed to reach this rank"
+        )
+        .required(false)
+        .min_int_value(0))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "perks",
+            "Whether reaching this rank unlocks perks (announced when reached)"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "announcement",
+            "Perk announcement text; {user} and {channel} mention the user and rank request channel"
+        )
+        .required(false)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("dink_replay")
+        .description("ADMIN: Re-process a stored Dink event, or list the ones that didn't go through.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "id",
+            "The Dink event to replay (default: list unprocessed events)"
+        )
+        .required(false)
+        .min_int_value(1))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "force",
+            "Replay even if it was already processed (refused while what it credited is still recorded)"
+        )
+        .required(false)))
+    .await?;
+
+    let mut achievement_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "achievement",
+        "The achievement to set points for"
+    )
+    .required(false);
+    for (key, name) in achievements::ACHIEVEMENTS {
+        achievement_option = achievement_option.add_string_choice(name, key);
+    }
+    Command::create_global_command(&ctx.http, CreateCommand::new("achievement_points")
+        .description("ADMIN: Set the points Dink achievements (99s, quest cape, CAs, diaries) are worth, or list them.")
+        .add_option(achievement_option)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "points",
+            "Points awarded for it (0 to only announce it)"
+        )
+        .required(false)
+        .min_int_value(0)))
+    .await?;
+
+    Command::create_global_command(&ctx.http, CreateCommand::new("command_permissions")
+        .description("ADMIN: Set who can use a command, or list the commands that are restricted.")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "command",
+            "The command to restrict, without the / (leave empty to list them)"
+        )
+        .required(false))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "tier",
+            "Who can use it"
+        )
+        .required(false)
+        .add_string_choice("Everyone", "everyone")
+        .add_string_choice("Moderators and admins", "moderator")
+        .add_string_choice("Admins only", "admin")))
+    .await?;
+
     Ok(())
 }
 
 pub async fn handle_interaction(ctx: &Context, interaction: &Interaction, db: &SqlitePool) -> Result<()> {
     match interaction {
         Interaction::Command(command) => {
+            if !is_allowed(ctx, db, command.member.as_deref(), &command.data.name).await? {
+                command
+                    .create_response(&ctx.http, CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("Sorry kitten, you're not allowed to do that.")
+                            .ephemeral(true)
+                    ))
+                    .await?;
+                return Ok(());
+            }
             match command.data.name.as_str() {
                 "drop" => handle_drop(command, ctx, db).await?,
                 "drop_remove" => handle_drop_remove(command, ctx, db).await?,
+                "undo" => handle_undo(command, ctx, db).await?,
                 "clog" => handle_clog(command, ctx, db).await?,
                 "clog_remove" => handle_clog_remove(command, ctx, db).await?,
                 "points" => handle_points(command, ctx, db).await?,
                 "leaderboard" => handle_leaderboard(command, ctx, db).await?,
                 "stats" => handle_stats(command, ctx, db).await?,
+                "kc" => handle_kc(command, ctx, db).await?,
+                "pb_board" => handle_pb_board(command, ctx, db).await?,
                 "rsname" => handle_rsname(command, ctx, db).await?,
                 "rsname_remove" => handle_rsname_remove(command, ctx, db).await?,
                 "rsnames" => handle_rsnames(command, ctx, db).await?,
@@ -181,19 +654,63 @@ pub async fn handle_interaction(ctx: &Context, interaction: &Interaction, db: &S
                 "unclamp" => handle_clamp(command, ctx, db, false).await?,
                 "whitelist" => handle_whitelist(command, ctx, db, true).await?,
                 "unwhitelist" => handle_whitelist(command, ctx, db, false).await?,
+                "audit_points" => handle_audit_points(command, ctx, db).await?,
+                "admin_drop_add" => handle_admin_drop_add(command, ctx, db).await?,
+                "admin_drop_remove" => handle_admin_drop_remove(command, ctx, db).await?,
+                "admin_clog_add" => handle_admin_clog_add(command, ctx, db).await?,
+                "admin_clog_remove" => handle_admin_clog_remove(command, ctx, db).await?,
+                "admin_points_adjust" => handle_admin_points_adjust(command, ctx, db).await?,
+                "rank_list" => handle_rank_list(command, ctx, db).await?,
+                "rank_add" => handle_rank_add(command, ctx, db).await?,
+                "rank_remove" => handle_rank_remove(command, ctx, db).await?,
+                "rank_edit" => handle_rank_edit(command, ctx, db).await?,
+                "dink_replay" => handle_dink_replay(command, ctx, db).await?,
+                "achievement_points" => handle_achievement_points(command, ctx, db).await?,
+                "point_rules" => handle_point_rules(command, ctx, db).await?,
+                "command_permissions" => handle_command_permissions(command, ctx, db).await?,
+                "clog_curve" => handle_clog_curve(command, ctx, db).await?,
+                "price_audit" => handle_price_audit(command, ctx, db).await?,
+                "price_override" => handle_price_override(command, ctx, db).await?,
                 _ => {
                     error!("Unknown command: {}", command.data.name);
                 }
             }
         }
         Interaction::Autocomplete(autocomplete) => {
+            // The admin commands' suggestions list other people's drops and clogs
+            if !is_allowed(ctx, db, autocomplete.member.as_deref(), &autocomplete.data.name).await? {
+                return Ok(());
+            }
             match autocomplete.data.name.as_str() {
-                "drop" | "clog" | "whitelist" | "unwhitelist" => {
+                "clog" | "admin_clog_add" => {
+                    if let Some(partial) = autocomplete.data.options.iter().find(|opt| opt.name == "item").and_then(|opt| opt.value.as_str()) {
+                        let data = ctx.data.read().await;
+
+                        // The item id goes back as the value, so /clog never has to guess between items with the same name
+                        let suggestions = if let Some(clog_manager) = data.get::<CollectionLogManagerKey>() {
+                            clog_manager.get_id_suggestions(partial).await
+                        } else {
+                            Vec::new()
+                        };
+
+                        let choices: Vec<AutocompleteChoice> = suggestions
+                            .into_iter()
+                            .map(|(label, item_id)| AutocompleteChoice::new(label, item_id.to_string()))
+                            .collect();
+
+                        autocomplete.create_response(&ctx.http,
+                            CreateInteractionResponse::Autocomplete(
+                                CreateAutocompleteResponse::new().set_choices(choices)
+                            )
+                        ).await?;
+                    }
+                }
+                "drop" | "admin_drop_add" | "whitelist" | "unwhitelist" | "point_rules" | "price_override" => {
                     if let Some(option) = autocomplete.data.options.iter().find(|opt| opt.name == "item" && opt.value.as_str().is_some()) {
                         if let Some(partial) = option.value.as_str() {
                             let data = ctx.data.read().await;
                             
-                            let suggestions = if autocomplete.data.name == "drop" {
+                            let suggestions = if matches!(autocomplete.data.name.as_str(), "drop" | "admin_drop_add" | "point_rules" | "price_override") {
                                 // Get price manager for drop suggestions
                                 if let Some(price_manager) = data.get::<PriceManagerKey>() {
                                     price_manager.get_item_suggestions(partial).await
@@ -222,15 +739,21 @@ pub async fn handle_interaction(ctx: &Context, interaction: &Interaction, db: &S
                         }
                     }
                 }
-                "drop_remove" | "clog_remove" => {
+                "drop_remove" | "clog_remove" | "admin_drop_remove" | "admin_clog_remove" => {
                     if let Some(option) = autocomplete.data.options.iter().find(|opt| opt.name == "id") {
-                        let discord_id = autocomplete.user.id.to_string();
+                        // The admin versions list whoever's been picked in the user option
+                        let discord_id = autocomplete.data.options
+                            .iter()
+                            .find(|opt| opt.name == "user")
+                            .and_then(|opt| opt.value.as_user_id())
+                            .unwrap_or(autocomplete.user.id)
+                            .to_string();
                         
-                        let recent_items = if autocomplete.data.name == "drop_remove" {
+                        let recent_items = if autocomplete.data.name.ends_with("drop_remove") {
                             // Get recent drops
                             sqlx::query!(
                                 "SELECT id, item_name, quantity, timestamp FROM drops 
-                                 WHERE discord_id = ? 
+                                 WHERE discord_id = ? AND deleted_at IS NULL
                                  ORDER BY timestamp DESC 
                                  LIMIT 25",
                                 discord_id
@@ -254,10 +777,10 @@ pub async fn handle_interaction(ctx: &Context, interaction: &Interaction, db: &S
                         } else {
                             // Get recent clog entries
                             sqlx::query!(
-                                "SELECT id, item_name, timestamp FROM collection_log_entries 
-                                 WHERE discord_id = ? 
+                                r#"SELECT id as "id!", item_name, timestamp FROM collection_log_entries 
+                                 WHERE discord_id = ? AND deleted_at IS NULL
                                  ORDER BY timestamp DESC 
-                                 LIMIT 25",
+                                 LIMIT 25"#,
                                 discord_id
                             )
                             .fetch_all(db)
@@ -305,9 +828,61 @@ pub async fn handle_interaction(ctx: &Context, interaction: &Interaction, db: &S
                         }
                     }
                 }
+                "rank_remove" | "rank_edit" => {
+                    if let Some(option) = autocomplete.data.options.iter().find(|opt| opt.name == "rank" && opt.value.as_str().is_some()) {
+                        if let Some(partial) = option.value.as_str() {
+                            let choices: Vec<AutocompleteChoice> = sqlx::query!(
+                                "SELECT role_name FROM rank_thresholds
+                                 WHERE role_name LIKE '%' || ? || '%'
+                                 ORDER BY points ASC
+                                 LIMIT 25",
+                                partial
+                            )
+                            .fetch_all(db)
+                            .await?
+                            .into_iter()
+                            .map(|row| AutocompleteChoice::new(row.role_name.clone(), row.role_name))
+                            .collect();
+
+                            autocomplete.create_response(&ctx.http,
+                                CreateInteractionResponse::Autocomplete(
+                                    CreateAutocompleteResponse::new().set_choices(choices)
+                                )
+                            ).await?;
+                        }
+                    }
+                }
+                "kc" | "pb_board" => {
+                    if let Some(option) = autocomplete.data.options.iter().find(|opt| opt.name == "boss" && opt.value.as_str().is_some()) {
+                        if let Some(partial) = option.value.as_str() {
+                            let choices: Vec<AutocompleteChoice> = sqlx::query!(
+                                "SELECT boss FROM kill_counts
+                                 WHERE boss LIKE '%' || ? || '%'
+                                 GROUP BY boss
+                                 ORDER BY SUM(kill_count) DESC
+                                 LIMIT 25",
+                                partial
+                            )
+                            .fetch_all(db)
+                            .await?
+                            .into_iter()
+                            .map(|row| AutocompleteChoice::new(row.boss.clone(), row.boss))
+                            .collect();
+
+                            autocomplete.create_response(&ctx.http,
+                                CreateInteractionResponse::Autocomplete(
+                                    CreateAutocompleteResponse::new().set_choices(choices)
+                                )
+                            ).await?;
+                        }
+                    }
+                }
                 _ => {}
             }
         }
+        Interaction::Component(component) if component.data.custom_id.starts_with(APPROVAL_PREFIX) => {
+            handle_approval_button(component, ctx, db).await?;
+        }
         _ => {}
     }
     
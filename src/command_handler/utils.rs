@@ -15,4 +15,42 @@ pub fn format_gp(n: i64) -> String {
 
 pub fn format_points(n: i64) -> String {
     format!("{} points", format_number(n))
-} 
\ No newline at end of file
+} 
+/// Parses the ISO-8601 durations Dink sends for kill times (e.g. "PT1M15.6S") into milliseconds
+pub fn parse_iso_duration(duration: &str) -> Option<i64> {
+    let rest = duration.strip_prefix("PT")?;
+    let mut total_ms = 0.0;
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'H' | 'M' | 'S' => {
+                let value = number.parse::<f64>().ok()?;
+                total_ms += value * match c {
+                    'H' => 3_600_000.0,
+                    'M' => 60_000.0,
+                    _ => 1_000.0,
+                };
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(total_ms.round() as i64)
+}
+
+/// Formats a kill time the way the game does, e.g. 1:15.60 or 1:02:03.00
+pub fn format_duration(ms: i64) -> String {
+    let hours = ms / 3_600_000;
+    let minutes = (ms / 60_000) % 60;
+    let seconds = (ms / 1_000) % 60;
+    let hundredths = (ms % 1_000) / 10;
+    if hours > 0 {
+        format!("{}:{:02}:{:02}.{:02}", hours, minutes, seconds, hundredths)
+    } else {
+        format!("{}:{:02}.{:02}", minutes, seconds, hundredths)
+    }
+}
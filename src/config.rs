@@ -10,6 +10,15 @@ pub struct Config {
     pub runelite_channel_id: Option<ChannelId>,
     pub rank_request_channel_id: Option<ChannelId>,
     pub dink_webhook_token: String,
+    pub dink_replay_token: Option<String>,
+    pub dink_replay_port: u16,
+    pub pet_points: Option<i64>,
+    pub approval_value_threshold: Option<i64>,
+    pub approval_points_threshold: Option<i64>,
+    pub evidence_value_threshold: Option<i64>,
+    pub evidence_points_threshold: Option<i64>,
+    pub moderator_role_id: Option<RoleId>,
+    pub admin_role_id: Option<RoleId>,
 }
 
 impl Config {
@@ -46,12 +55,44 @@ impl Config {
         // custom headers - this is the only thing gating that endpoint from the open internet
         let dink_webhook_token = env::var("DINK_WEBHOOK_TOKEN")?;
 
+        // Optional secret for the dink_replay CLI, which talks to a separate listener on
+        // 127.0.0.1 (DINK_REPLAY_PORT, 3001 by default); unset means replays only via /dink_replay
+        let dink_replay_token = env::var("DINK_REPLAY_TOKEN").ok().filter(|token| !token.is_empty());
+        let dink_replay_port = env::var("DINK_REPLAY_PORT").ok().and_then(|port| port.parse::<u16>().ok()).unwrap_or(3001);
+
+        // Optional flat points for every new pet; unset means pets are scored on their
+        // collection log rarity like any other clog
+        let pet_points = env::var("PET_POINTS").ok().and_then(|points| points.parse::<i64>().ok());
+
+        // Optional thresholds above which manual /drop and /clog submissions wait for a mod to
+        // approve them; unset means everything is credited straight away
+        let approval_value_threshold = env::var("APPROVAL_VALUE_THRESHOLD").ok().and_then(|value| value.parse::<i64>().ok());
+        let approval_points_threshold = env::var("APPROVAL_POINTS_THRESHOLD").ok().and_then(|points| points.parse::<i64>().ok());
+
+        // Optional thresholds above which manual submissions have to come with a screenshot
+        let evidence_value_threshold = env::var("EVIDENCE_VALUE_THRESHOLD").ok().and_then(|value| value.parse::<i64>().ok());
+        let evidence_points_threshold = env::var("EVIDENCE_POINTS_THRESHOLD").ok().and_then(|points| points.parse::<i64>().ok());
+
+        // Optional staff roles for the command_permissions tiers. Server admins always count as
+        // admins, so with neither set only they can use staff commands
+        let moderator_role_id = env::var("MODERATOR_ROLE_ID").ok().and_then(|id| id.parse::<u64>().ok()).map(RoleId::new);
+        let admin_role_id = env::var("ADMIN_ROLE_ID").ok().and_then(|id| id.parse::<u64>().ok()).map(RoleId::new);
+
         Ok(Self {
             mod_channel_id: ChannelId::new(mod_channel_id),
             log_channel_id: ChannelId::new(log_channel_id),
             runelite_channel_id,
             rank_request_channel_id,
             dink_webhook_token,
+            dink_replay_token,
+            dink_replay_port,
+            pet_points,
+            approval_value_threshold,
+            approval_points_threshold,
+            evidence_value_threshold,
+            evidence_points_threshold,
+            moderator_role_id,
+            admin_role_id,
         })
     }
 }
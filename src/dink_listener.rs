@@ -10,15 +10,18 @@ use serenity::all::{CreateMessage, CreateEmbed, Member, GuildId, ChannelId};
 use sqlx::SqlitePool;
 use axum::{
     body::Bytes,
-    extract::{Request, Multipart, FromRequest, Path},
-    http::{header::CONTENT_TYPE, StatusCode},
+    extract::{Request, Multipart, FromRequest, Path, Query},
+    http::{header::{AUTHORIZATION, CONTENT_TYPE}, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
-use tracing::{error, debug};
+use tracing::{error, debug, info};
 use crate::DinkHandler;
 use serde::Deserialize;
+use std::collections::HashMap;
 use crate::logger;
 use crate::rank_manager;
+use crate::scoring;
+use crate::prices::DropPrice;
 use crate::command_handler::utils;
 
 // https://github.com/pajlads/DinkPlugin/blob/master/docs/json-examples.md
@@ -56,6 +59,46 @@ struct DinkExtra {
     dropper_name: Option<String>,
     dropper_type: Option<String>,
     dropper_kill_count: Option<String>,
+    // Level category
+    levelled_skills: Option<HashMap<String, i32>>,
+    all_skills: Option<HashMap<String, i32>>,
+    combat_level: Option<DinkCombatLevel>,
+    // Quest category
+    quest_name: Option<String>,
+    completed_quests: Option<i32>,
+    total_quests: Option<i32>,
+    quest_points: Option<i32>,
+    total_quest_points: Option<i32>,
+    // Combat achievement category
+    // (not `tier` - leagues relic notifications send that as a number, which would fail to parse)
+    task: Option<String>,
+    task_points: Option<i32>,
+    total_points: Option<i32>,
+    total_possible_points: Option<i32>,
+    current_tier: Option<String>,
+    next_tier: Option<String>,
+    just_completed_tier: Option<String>,
+    // Achievement diary category
+    area: Option<String>,
+    difficulty: Option<String>,
+    total: Option<i32>,
+    tasks_completed: Option<i32>,
+    tasks_total: Option<i32>,
+    area_tasks_completed: Option<i32>,
+    area_tasks_total: Option<i32>,
+    // Kill count category
+    boss: Option<String>,
+    count: Option<i32>,
+    time: Option<String>,
+    is_personal_best: Option<bool>,
+    personal_best: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DinkCombatLevel {
+    value: i32,
+    increased: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -84,7 +127,7 @@ struct DinkFile {
     content: Bytes,
 }
 
-pub async fn dink_handler(Extension(handler): Extension<DinkHandler>, Path(token): Path<String>, req: Request) -> Response {
+pub async fn dink_handler(Extension(mut handler): Extension<DinkHandler>, Path(token): Path<String>, req: Request) -> Response {
     // The token is the only thing gating this endpoint - Dink can't send custom headers, so it
     // has to live in the URL path itself. Distribute it only via the hosted, importable Dink
     // config, not by hand. 404 (not 401) so the endpoint's existence isn't confirmed either way.
@@ -160,19 +203,316 @@ pub async fn dink_handler(Extension(handler): Extension<DinkHandler>, Path(token
     };
 
     debug!("Payload length: {}", payload_json.len());
+    // Keep the raw payload before we try to make sense of it, so anything that goes wrong from
+    // here on can be replayed later (see replay_dink_event)
+    let inbox_id = store_in_inbox(&handler.db, &payload_json, dink_file.as_ref()).await;
+
     let data: DinkPayload = match serde_json::from_slice(&payload_json) {
         Ok(data) => data,
         Err(e) => {
             error!("Failed to parse Dink payload: {:?}", e);
+            set_inbox_status(&handler.db, inbox_id, DinkStatus::Unparseable, Some(&e.to_string())).await;
             return StatusCode::BAD_REQUEST.into_response();
         }
     };
 
-    process_dink_event(handler, data, dink_file).await;
+    // Duplicates still get a 200 - as far as Dink is concerned it was delivered fine, and an
+    // error would only make it retry again
+    let event_id = match register_dink_event(&handler.db, &data).await {
+        Ok(Some(event_id)) => Some(event_id),
+        Ok(None) => {
+            info!("Ignoring duplicate Dink {} event from {}", data.notif_type, data.player_name);
+            let _ = logger::log_generic(
+                &handler.ctx,
+                &format!("DUPLICATE DINK EVENT: Ignored a repeat {} notification from RSN {}", data.notif_type, data.player_name)
+            ).await;
+            set_inbox_status(&handler.db, inbox_id, DinkStatus::Duplicate, None).await;
+            return StatusCode::OK.into_response();
+        }
+        Err(e) => {
+            // Better to risk a double credit than to lose a drop because the table hiccuped
+            error!("Failed to check Dink event for duplicates, processing anyway: {:?}", e);
+            None
+        }
+    };
+
+    let db = handler.db.clone();
+    handler.inbox_id = inbox_id;
+    let status = process_dink_event(handler, data, dink_file).await;
+    // Nothing was credited, so a retry of the same event mustn't be turned away as a duplicate
+    if status == DinkStatus::Failed {
+        if let Some(event_id) = event_id {
+            forget_dink_event(&db, event_id).await;
+        }
+    }
+    set_inbox_status(&db, inbox_id, status, None).await;
     StatusCode::OK.into_response()
 }
 
-async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_file: Option<DinkFile>) {
+/// How far a Dink event got, kept in dink_inbox.status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DinkStatus {
+    /// Handled (points credited and/or notification posted)
+    Processed,
+    /// Same event as one we already handled (see register_dink_event)
+    Duplicate,
+    /// Couldn't be read as a DinkPayload
+    Unparseable,
+    /// A notification type we don't handle (yet)
+    Unsupported,
+    /// Couldn't work out whose account sent it
+    UnknownUser,
+    /// Deliberately skipped (leagues, missing item data)
+    Ignored,
+    /// Crediting it went wrong (a database error) - nothing from the failed step was recorded,
+    /// so it can be replayed
+    Failed,
+}
+
+impl DinkStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DinkStatus::Processed => "processed",
+            DinkStatus::Duplicate => "duplicate",
+            DinkStatus::Unparseable => "unparseable",
+            DinkStatus::Unsupported => "unsupported",
+            DinkStatus::UnknownUser => "unknown_user",
+            DinkStatus::Ignored => "ignored",
+            DinkStatus::Failed => "failed",
+        }
+    }
+}
+
+// Only the screenshot's name and size are kept - the image itself is posted to Discord anyway,
+// and storing every screenshot would balloon the database
+async fn store_in_inbox(db: &SqlitePool, payload_json: &Bytes, dink_file: Option<&DinkFile>) -> Option<i64> {
+    let payload = String::from_utf8_lossy(payload_json);
+    let screenshot_name = dink_file.map(|f| f.file_name.clone());
+    let screenshot_size = dink_file.map(|f| f.content.len() as i64);
+
+    match sqlx::query!(
+        "INSERT INTO dink_inbox (payload, screenshot_name, screenshot_size) VALUES (?, ?, ?)",
+        payload,
+        screenshot_name,
+        screenshot_size
+    )
+    .execute(db)
+    .await
+    {
+        Ok(result) => Some(result.last_insert_rowid()),
+        Err(e) => {
+            error!("Failed to store Dink payload in inbox: {:?}", e);
+            None
+        }
+    }
+}
+
+async fn set_inbox_status(db: &SqlitePool, inbox_id: Option<i64>, status: DinkStatus, error: Option<&str>) {
+    let Some(inbox_id) = inbox_id else { return };
+    let status = status.as_str();
+    if let Err(e) = sqlx::query!(
+        "UPDATE dink_inbox SET status = ?, error = ?, processed_at = CURRENT_TIMESTAMP WHERE id = ?",
+        status,
+        error,
+        inbox_id
+    )
+    .execute(db)
+    .await
+    {
+        error!("Failed to update Dink inbox entry {}: {:?}", inbox_id, e);
+    }
+}
+
+/// Why a stored payload couldn't be replayed
+#[derive(Debug)]
+pub enum ReplayError {
+    NotFound,
+    /// Already credited (or still pending, so possibly being credited right now) - replaying
+    /// could credit it again. Needs `force`.
+    AlreadyProcessed(String),
+    /// The drops/collection log entries it credited are still there - not even `force` replays
+    /// those (remove them first if it really has to be redone)
+    AlreadyCredited(i64),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::NotFound => write!(f, "no such inbox entry"),
+            ReplayError::AlreadyProcessed(status) => write!(f, "entry is already {} - replaying it would credit it twice", status),
+            ReplayError::AlreadyCredited(count) => write!(f, "entry already credited {} drop(s)/collection log entries - remove those before replaying it", count),
+            ReplayError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+/// Re-runs a stored payload from dink_inbox through process_dink_event, e.g. after a bug fix or
+/// once an unknown player has linked their account. Entries that were already processed (or
+/// were duplicates of something processed, or are still pending) are refused unless `force` is
+/// set, since they'd be credited twice, and even with `force` an entry whose drops or collection
+/// log entries are still recorded is refused, whatever its status says. The screenshot isn't
+/// stored, so replays are posted without it.
+pub async fn replay_dink_event(mut handler: DinkHandler, inbox_id: i64, force: bool) -> Result<DinkStatus, ReplayError> {
+    let entry = sqlx::query!("SELECT payload, status FROM dink_inbox WHERE id = ?", inbox_id)
+        .fetch_optional(&handler.db)
+        .await
+        .map_err(ReplayError::Database)?
+        .ok_or(ReplayError::NotFound)?;
+
+    // 'pending' is the column default until the handler sets a status - it's either still being
+    // processed or crashed partway through
+    let needs_force = [DinkStatus::Processed.as_str(), DinkStatus::Duplicate.as_str(), "pending"].contains(&entry.status.as_str());
+    if !force && needs_force {
+        return Err(ReplayError::AlreadyProcessed(entry.status));
+    }
+    // Go by what's actually recorded rather than the status, which can lag behind a crash
+    let credited = sqlx::query_scalar!(
+        r#"SELECT (SELECT COUNT(*) FROM drops WHERE dink_inbox_id = ?1 AND deleted_at IS NULL)
+                + (SELECT COUNT(*) FROM collection_log_entries WHERE dink_inbox_id = ?1 AND deleted_at IS NULL) as "credited!: i64""#,
+        inbox_id
+    )
+    .fetch_one(&handler.db)
+    .await
+    .map_err(ReplayError::Database)?;
+    if credited > 0 {
+        return Err(ReplayError::AlreadyCredited(credited));
+    }
+
+    let data: DinkPayload = match serde_json::from_str(&entry.payload) {
+        Ok(data) => data,
+        Err(e) => {
+            set_inbox_status(&handler.db, Some(inbox_id), DinkStatus::Unparseable, Some(&e.to_string())).await;
+            return Ok(DinkStatus::Unparseable);
+        }
+    };
+
+    info!("Replaying Dink inbox entry {} ({} from {})", inbox_id, data.notif_type, data.player_name);
+    let db = handler.db.clone();
+    handler.inbox_id = Some(inbox_id);
+    let status = process_dink_event(handler, data, None).await;
+    set_inbox_status(&db, Some(inbox_id), status, None).await;
+    Ok(status)
+}
+
+/// Endpoint for the dink_replay CLI, which can't run process_dink_event itself since it has no
+/// Discord connection. Served on its own listener bound to 127.0.0.1 (see main.rs), and gated
+/// by DINK_REPLAY_TOKEN as a bearer token rather than the webhook token every player has.
+pub async fn dink_replay_handler(
+    Extension(handler): Extension<DinkHandler>,
+    Path(inbox_id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let token = headers.get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if handler.config.dink_replay_token.is_none() || token != handler.config.dink_replay_token.as_deref() {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let force = params.get("force").is_some_and(|v| v == "true");
+
+    match replay_dink_event(handler, inbox_id, force).await {
+        Ok(status) => (StatusCode::OK, status.as_str()).into_response(),
+        Err(ReplayError::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(e @ (ReplayError::AlreadyProcessed(_) | ReplayError::AlreadyCredited(_))) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        Err(e) => {
+            error!("Failed to replay Dink inbox entry {}: {}", inbox_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub struct DinkHandlerKey;
+
+impl TypeMapKey for DinkHandlerKey {
+    type Value = DinkHandler;
+}
+
+// Repeats of the same fingerprint inside this window are treated as the same event
+const DUPLICATE_WINDOW_SECS: i64 = 300;
+
+/// What makes two Dink notifications the same event: same account, same type, same items and
+/// the same kill count (or for achievements, the same skills/quest/task/diary). A RuneLite retry or a duplicated webhook resends all of these unchanged,
+/// whereas a genuine second drop will (almost always) be on a different kill count - and if it
+/// isn't, it has to land outside DUPLICATE_WINDOW_SECS to count.
+fn dink_fingerprint(data: &DinkPayload) -> String {
+    let mut items: Vec<String> = data.extra.items.iter()
+        .flatten()
+        .map(|item| format!("{}x{}", item.id, item.quantity))
+        .collect();
+    items.sort();
+
+    let kill_count = data.extra.kill_count
+        .or(data.extra.count)
+        .map(|kc| kc.to_string())
+        .or_else(|| data.extra.dropper_kill_count.clone())
+        .unwrap_or_default();
+
+    let mut levels: Vec<String> = data.extra.levelled_skills.iter()
+        .flatten()
+        .map(|(skill, level)| format!("{}={}", skill, level))
+        .collect();
+    levels.sort();
+    let achievement = [
+        levels.join(","),
+        data.extra.quest_name.clone().unwrap_or_default(),
+        data.extra.task.clone().unwrap_or_default(),
+        data.extra.area.clone().unwrap_or_default(),
+        data.extra.difficulty.clone().unwrap_or_default(),
+    ].join("/");
+
+    format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}",
+        data.dink_account_hash,
+        data.notif_type,
+        items.join(","),
+        data.extra.item_id.map(|id| id.to_string()).unwrap_or_default(),
+        data.extra.pet_name.clone().unwrap_or_default(),
+        data.extra.source.clone().or_else(|| data.extra.boss.clone()).unwrap_or_default(),
+        kill_count,
+        achievement
+    )
+}
+
+/// Records the event in dink_events unless the same fingerprint was already seen inside the
+/// duplicate window. It's one INSERT ... WHERE NOT EXISTS, so two copies arriving at the same
+/// moment can't both get through.
+/// Returns the dink_events id, or None if it's a duplicate.
+async fn register_dink_event(db: &SqlitePool, data: &DinkPayload) -> Result<Option<i64>, sqlx::Error> {
+    let fingerprint = dink_fingerprint(data);
+    let window = format!("-{} seconds", DUPLICATE_WINDOW_SECS);
+
+    let result = sqlx::query!(
+        "INSERT INTO dink_events (fingerprint, account_hash, notif_type, player_name)
+         SELECT ?, ?, ?, ?
+         WHERE NOT EXISTS (
+             SELECT 1 FROM dink_events WHERE fingerprint = ? AND received_at >= datetime('now', ?)
+         )",
+        fingerprint,
+        data.dink_account_hash,
+        data.notif_type,
+        data.player_name,
+        fingerprint,
+        window
+    )
+    .execute(db)
+    .await?;
+
+    Ok((result.rows_affected() > 0).then(|| result.last_insert_rowid()))
+}
+
+/// Takes an event back out of dink_events when processing it failed, so it isn't counted as seen
+async fn forget_dink_event(db: &SqlitePool, event_id: i64) {
+    if let Err(e) = sqlx::query!("DELETE FROM dink_events WHERE id = ?", event_id)
+        .execute(db)
+        .await
+    {
+        error!("Failed to forget failed Dink event {}: {:?}", event_id, e);
+    }
+}
+
+async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_file: Option<DinkFile>) -> DinkStatus {
     let config = &dink_handler.config;
     let screenshot = dink_file.map(|f| CreateAttachment::bytes(f.content, f.file_name));
     let mut author = CreateEmbedAuthor::new(data.player_name.clone());
@@ -200,12 +540,12 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                 &dink_handler.ctx,
                 &format!("UNKNOWN USER: Someone I couldn't find in the discord server tried to do something: RSN {} sent something with Dink of type {}", data.player_name, data.notif_type)
             ).await;
-            return;
+            return DinkStatus::UnknownUser;
         };
         if data.seasonal_world {
             //Don't care about leagues (for now)
             debug!("Ignoring Dink event from seasonal/league world for {}", data.player_name);
-            return;
+            return DinkStatus::Ignored;
         }
         {
             let footer = CreateEmbedFooter::new(member.display_name())
@@ -215,6 +555,7 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
             let discord_id = member.user.id.to_string();
 
             let mut sendable = false;
+            let mut status = DinkStatus::Processed;
 
             match data.notif_type.as_str() {
                 "COLLECTION" => {
@@ -222,7 +563,7 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                     debug!("Received collection log");
                     let Some(id) = data.extra.item_id else {
                         debug!("COLLECTION event with no itemId, dropping");
-                        return;
+                        return DinkStatus::Ignored;
                     };
                     let item = sqlx::query!("SELECT * FROM v_item_data WHERE item_id = ?", id)
                     .fetch_one(&dink_handler.db)
@@ -232,27 +573,16 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                     let description: String;
                     if let Some(item) = item {
                         let item_name = item.preferred_name.clone();
-                        //Do they have this item recorded already?
-                        if let Ok(Some(_)) = sqlx::query!(
-                            "SELECT id FROM collection_log_entries 
-                            WHERE discord_id = ? AND item_name = ?",
-                            discord_id,
-                            item_name
-                        )
-                        .fetch_optional(&dink_handler.db)
-                        .await
-                        {
-                            debug!("User {} already has collection log entry for {}", discord_id, item_name);
-                            description = format!("Got a collection log item:\n**{}**!\n\n...But it was already recorded!", search_link(item_name.clone()));
-
-                            let _ = logger::log_action(
-                                &dink_handler.ctx,
-                                &discord_id,
-                                "DINK CLOG",
-                                &format!("{} received collection log item they already had: {}", data.player_name, item_name)
-                            ).await;
-                        } else {
-                            let (points, new_total) = dink_clog(&dink_handler, id, item_name.clone(), discord_id.clone(), &member.display_name()).await;
+                        let points = dink_handler.collection_log_manager.calculate_points(id).await.unwrap_or(0);
+                        let recorded = match dink_clog(&dink_handler, id, item_name.clone(), points, discord_id.clone(), member.display_name()).await {
+                            Ok(recorded) => recorded,
+                            Err(e) => {
+                                error!("Failed to record Dink clog: {:?}", e);
+                                return DinkStatus::Failed;
+                            }
+                        };
+                        //None means they have this item recorded already
+                        if let Some(new_total) = recorded {
                             description = format!("Got a new collection log item:\n**{}**!", search_link(item_name.clone()));
                             //Now that we know for sure the item is valid we can build the embed
 
@@ -278,6 +608,16 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                                 "DINK CLOG",
                                 &format!("{} received collection log item: {} (+{} points)", data.player_name, item_name, points)
                             ).await;
+                        } else {
+                            debug!("User {} already has collection log entry for {}", discord_id, item_name);
+                            description = format!("Got a collection log item:\n**{}**!\n\n...But it was already recorded!", search_link(item_name.clone()));
+
+                            let _ = logger::log_action(
+                                &dink_handler.ctx,
+                                &discord_id,
+                                "DINK CLOG",
+                                &format!("{} received collection log item they already had: {}", data.player_name, item_name)
+                            ).await;
                         }
                         
                     }
@@ -287,7 +627,10 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                         //We don't have data so we kinda just have to abandon ship
                         description = format!("Got a new collection log item:\n**{}**!\n\nBut, ummm... I don't know what that is yet... sorry...", search_link(item_name.clone()));
                         //We can still add the record but no points will be added
-                        dink_clog(&dink_handler, id, item_name.clone(), discord_id.clone(), &member.display_name()).await;
+                        if let Err(e) = dink_clog(&dink_handler, id, item_name.clone(), 0, discord_id.clone(), member.display_name()).await {
+                            error!("Failed to record Dink clog: {:?}", e);
+                            return DinkStatus::Failed;
+                        }
                         let _ = logger::log_action(
                                 &dink_handler.ctx,
                                 &discord_id,
@@ -300,59 +643,101 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                 }
                 "LOOT" => {
                     debug!("Received drop");
-                    //Currently, drops worth 100k+ gp = 1 point per 100k
+                    //Drops worth at least the minimum in point_rules (100k by default) get points, 1 per gp_per_point
                     //We first need to check if the drop is even worth that much
                     //Part of the reason for this update is because RDT uncut sapphires (for example) are technically ~1/170
                     //Technically *rare* when some valuable drops are 1/128, but when people had their drop plugins set improperly we'd get notified for basically everything
                     //Dink does not have rarity set for every drop (according to docs), so we'll just say if it's high enough value it's fine
                     let Some(items) = data.extra.items else {
                         debug!("LOOT event with no items, dropping");
-                        return;
+                        return DinkStatus::Ignored;
                     };
-                    let mut valuable: Option<DinkItem> = None;
-                    let mut best: i64 = 0;
-                    for (_i, item) in items.iter().enumerate() {
+                    let rules = scoring::PointRules::load(&dink_handler.db).await.unwrap_or_else(|e| {
+                        error!("Failed to load point rules, using the defaults: {:?}", e);
+                        scoring::PointRules::default()
+                    });
+                    //Every item worth it gets credited on its own (e.g. two purples from one raid), not just the best one
+                    let mut valuable: Vec<(DinkItem, i64, Option<DropPrice>)> = Vec::new(); // (item, value, price it was valued at)
+                    for item in items.iter() {
                         //The number might be low depending on the users RuneLite settings (shop sell price instead of GE price), so get more reliable numbers
                         //Alternatively:
                         //let price = item.price_each.max(dink_handler.price_manager.get_item_id_price(&item.id).await.unwrap_or(0i64));
-                        let price = dink_handler.price_manager.get_item_id_price(&item.id).await.unwrap_or(0);
-                        let value = item.quantity * price;
+                        let price = dink_handler.price_manager.get_drop_price(item.id).await;
+                        let value = item.quantity * price.as_ref().map_or(0, |price| price.price_each);
+                        //A flagged price has been capped (e.g. to high alch), so it's judged on what the market says instead -
+                        //otherwise a valuable but thinly traded drop would be skipped without the mods ever seeing the flag
+                        let qualifying_value = match price.as_ref().and_then(|price| price.flag.as_ref()) {
+                            Some(flag) => value.max(item.quantity * flag.market_price),
+                            None => value,
+                        };
                         //Annoyingly even if an item is in the denylist, it's still sent if we get other drop data, just with DENYLIST criteria
-                        if value >= 100_000 && value > best && !item.criteria.contains(&"DENYLIST".to_string()) {
-                            valuable = Some(item.clone());
-                            best = value;
+                        if rules.for_item(&item.name).qualifies(qualifying_value) && !item.criteria.contains(&"DENYLIST".to_string()) {
+                            valuable.push((item.clone(), value, price));
                         }
                     }
-                    if let Some(item) = valuable {
+                    //Most valuable first, it's the one that gets the thumbnail
+                    valuable.sort_by_key(|(_, value, _)| std::cmp::Reverse(*value));
+                    if let Some((best_item, _, _)) = valuable.first().cloned() {
                         //Now that we know it's valuable, we're okay to send
                         sendable = true;
-                        let points = best / 100_000;
                         let source = data.extra.source.clone().unwrap_or_else(|| "an unknown source".to_string());
-                        let description = if item.quantity > 1 {
-                            format!("Got {}x {} from {}!", item.quantity, search_link(item.name.clone()), search_link(source))
+
+                        let mut total_value = 0;
+                        let mut points = 0;
+                        let mut new_total = 0;
+                        let mut lines: Vec<String> = Vec::new();
+                        for (item, value, price) in &valuable {
+                            let item_points = rules.for_item(&item.name).points(*value);
+                            new_total = match dink_drop(&dink_handler, item, *value, price.as_ref(), item_points, discord_id.clone(), member.display_name()).await {
+                                Ok(recorded) => recorded,
+                                Err(e) => {
+                                    error!("Failed to record Dink drop: {:?}", e);
+                                    return DinkStatus::Failed;
+                                }
+                            };
+                            total_value += value;
+                            points += item_points;
+                            lines.push(if item.quantity > 1 {
+                                format!("{}x {}", item.quantity, search_link(item.name.clone()))
+                            } else {
+                                search_link(item.name.clone())
+                            });
                         }
-                        else {
-                            format!("Got {} from {}!", search_link(item.name.clone()), search_link(source))
+
+                        let description = if lines.len() == 1 {
+                            format!("Got {} from {}!", lines[0], search_link(source))
+                        } else {
+                            format!("Got some loot from {}!\n{}", search_link(source),
+                                lines.iter().zip(&valuable)
+                                    .map(|(line, (_, value, _))| format!("• {} ({})", line, utils::format_gp(*value)))
+                                    .collect::<Vec<_>>()
+                                    .join("\n"))
                         };
-                        if let Some(rarity_val) = item.rarity {
+                        //Rarity only makes sense for a single item
+                        if let Some(rarity_val) = best_item.rarity.filter(|_| valuable.len() == 1) {
                             let denom = (1.0 / rarity_val).round();
                             let rarity = rarity_val * 100.0;
                             embed = embed.field("Rarity (approx)", format!("```glsl\n# 1/{} ({:.2}%)```", denom, rarity), true);
                         }
-                        let new_total = dink_drop(&dink_handler, item.id, item.name.clone(), best, discord_id.clone(), &member.display_name()).await;
                         embed = embed.description(description)
-                        .field("GE Price", format_value(utils::format_gp(best)), true)
+                        .field("GE Price", format_value(utils::format_gp(total_value)), true)
                         .field("", "", false)
                         .field("Points Added", format_value(format!("+{}", points)), true)
                         .field("Points Total", format_value(new_total.to_string()), true)
-                        .thumbnail(format!("https://static.runelite.net/cache/item/icon/{}.png", item.id));
+                        .thumbnail(format!("https://static.runelite.net/cache/item/icon/{}.png", best_item.id));
 
                         // Log the auto-added drop to the bot log channel
                         let _ = crate::logger::log_action(
                             &dink_handler.ctx,
                             &discord_id,
                             "DINK DROP",
-                            &format!("{} received {}x {} worth {} GP", data.player_name, item.quantity, item.name, best)
+                            &format!("{} received {} worth {} GP",
+                                data.player_name,
+                                valuable.iter()
+                                    .map(|(item, _, _)| format!("{}x {}", item.quantity, item.name))
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                                total_value)
                         ).await;
                     }
                     else {
@@ -368,7 +753,6 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                 "PET" => {
                     sendable = true;
                     debug!("Received pet");
-                    //We don't process this for points (yet) so it's pretty simple, relatively
                     let description = if data.extra.duplicate.unwrap_or(false) {
                         "You have a funny feeling like you would've been followed..."
                     } else {
@@ -379,22 +763,255 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                         embed = embed.field("Pet", format_value(pet_name.clone()), true);
                         //Check db for item_id (Dink doesn't give this for pets)
                         let pet_row = sqlx::query!(
-                            "SELECT item_id, item_name FROM collection_log_items WHERE item_name = ?",
+                            "SELECT item_id, preferred_name, percentage FROM v_item_data WHERE item_name = ? OR preferred_name = ?",
+                            pet_name,
                             pet_name
                         )
                         .fetch_one(&dink_handler.db)
                         .await;
+                        if let Some(milestone) = data.extra.milestone.clone() { //Do we have a milestone?
+                            embed = embed.field("Milestone", format_value(milestone), true);
+                        }
                         if let Ok(pet_row) = pet_row {
                             let pet_id = pet_row.item_id;
+                            let item_name = pet_row.preferred_name;
                             embed = embed.thumbnail(format!("https://static.runelite.net/cache/item/icon/{pet_id}.png"));
+
+                            //Pets are collection log items, so they're credited the same way (and can be taken back with /clog_remove)
+                            let recorded = if data.extra.duplicate.unwrap_or(false) {
+                                None
+                            } else {
+                                //A flat PET_POINTS value if the clan set one, otherwise whatever its collection log rarity is worth
+                                let points = match config.pet_points {
+                                    Some(points) => points,
+                                    None => dink_handler.collection_log_manager.calculate_points(pet_id).await.unwrap_or(0),
+                                };
+                                match dink_clog(&dink_handler, pet_id, item_name.clone(), points, discord_id.clone(), member.display_name()).await {
+                                    Ok(recorded) => recorded.map(|new_total| (points, new_total)),
+                                    Err(e) => {
+                                        error!("Failed to record Dink clog: {:?}", e);
+                                        return DinkStatus::Failed;
+                                    }
+                                }
+                            };
+
+                            //None means they have this pet recorded already
+                            if let Some((points, new_total)) = recorded {
+                                embed = embed.field("Global Clog Rate", format_value(format!("{}%", pet_row.percentage)), true)
+                                    .field("", "", false)
+                                    .field("Points Added", format_value(format!("+{}", points)), true)
+                                    .field("Points Total", format_value(new_total.to_string()), true);
+
+                                let _ = logger::log_action(
+                                    &dink_handler.ctx,
+                                    &discord_id,
+                                    "DINK PET",
+                                    &format!("{} received a new pet: {} (+{} points)", data.player_name, item_name, points)
+                                ).await;
+                            } else {
+                                debug!("User {} already has pet {}", discord_id, item_name);
+                                let _ = logger::log_action(
+                                    &dink_handler.ctx,
+                                    &discord_id,
+                                    "DINK PET",
+                                    &format!("{} received a pet they already had: {}", data.player_name, item_name)
+                                ).await;
+                            }
                         }
-                        if let Some(milestone) = data.extra.milestone.clone() { //Do we have a milestone?
-                            embed = embed.field("Milestone", format_value(milestone), true);
+                    }
+                }
+                "LEVEL" => {
+                    debug!("Received level up");
+                    //Dink sends every level up the user's plugin is set to notify for, but we only celebrate 99s
+                    let mut maxed: Vec<String> = data.extra.levelled_skills.clone().unwrap_or_default()
+                        .into_iter()
+                        .filter(|(_, level)| *level == 99)
+                        .map(|(skill, _)| skill)
+                        .collect();
+                    if maxed.is_empty() {
+                        debug!("LEVEL event with no 99s, dropping");
+                        return DinkStatus::Ignored;
+                    }
+                    sendable = true;
+                    maxed.sort();
+
+                    let mut points = 0;
+                    let mut new_total = None;
+                    for skill in &maxed {
+                        let credited = match dink_achievement(&dink_handler, "LEVEL_99", skill, &discord_id, &data.player_name, member.display_name()).await {
+                            Ok(credited) => credited,
+                            Err(e) => {
+                                error!("Failed to record Dink achievement: {:?}", e);
+                                return DinkStatus::Failed;
+                            }
+                        };
+                        if let Some((awarded, total)) = credited {
+                            points += awarded;
+                            new_total = Some(total);
+                        }
+                    }
+
+                    embed = embed.description(format!("Reached level 99 in **{}**!", maxed.join("** and **")))
+                        .thumbnail(format!("https://oldschool.runescape.wiki/images/{}_cape.png", maxed[0]));
+                    if let Some(skills) = &data.extra.all_skills {
+                        let total_level: i32 = skills.values().sum();
+                        embed = embed.field("Total Level", format_value(total_level.to_string()), true);
+                    }
+                    if let Some(combat) = &data.extra.combat_level {
+                        if combat.increased {
+                            embed = embed.field("Combat Level", format_value(combat.value.to_string()), true);
+                        }
+                    }
+                    embed = achievement_points_fields(embed, points, new_total);
+
+                    let _ = logger::log_action(
+                        &dink_handler.ctx,
+                        &discord_id,
+                        "DINK LEVEL",
+                        &format!("{} reached 99 {} (+{} points)", data.player_name, maxed.join(", "), points)
+                    ).await;
+                }
+                "QUEST" => {
+                    debug!("Received quest completion");
+                    //Only the quest cape is worth shouting about
+                    let (Some(completed), Some(total)) = (data.extra.completed_quests, data.extra.total_quests) else {
+                        debug!("QUEST event without quest counts, dropping");
+                        return DinkStatus::Ignored;
+                    };
+                    if completed < total {
+                        debug!("QUEST event that isn't the last quest, dropping");
+                        return DinkStatus::Ignored;
+                    }
+                    sendable = true;
+
+                    let credited = match dink_achievement(&dink_handler, "QUEST_CAPE", "", &discord_id, &data.player_name, member.display_name()).await {
+                        Ok(recorded) => recorded,
+                        Err(e) => {
+                            error!("Failed to record Dink achievement: {:?}", e);
+                            return DinkStatus::Failed;
+                        }
+                    };
+                    let quest_name = data.extra.quest_name.clone().unwrap_or_else(|| "their last quest".to_string());
+                    embed = embed.description(format!("Completed **{}** and earned the **{}**!", quest_name, search_link("Quest point cape".to_string())))
+                        .thumbnail("https://oldschool.runescape.wiki/images/Quest_point_cape.png")
+                        .field("Quests", format_value(format!("{}/{}", completed, total)), true);
+                    if let (Some(quest_points), Some(total_quest_points)) = (data.extra.quest_points, data.extra.total_quest_points) {
+                        embed = embed.field("Quest Points", format_value(format!("{}/{}", quest_points, total_quest_points)), true);
+                    }
+                    embed = achievement_points_fields(embed, credited.map_or(0, |(points, _)| points), credited.map(|(_, total)| total));
+
+                    let _ = logger::log_action(
+                        &dink_handler.ctx,
+                        &discord_id,
+                        "DINK QUEST",
+                        &format!("{} earned the quest cape (+{} points)", data.player_name, credited.map_or(0, |(points, _)| points))
+                    ).await;
+                }
+                "COMBAT_ACHIEVEMENT" => {
+                    debug!("Received combat achievement");
+                    //Individual tasks are far too frequent to post, only whole tiers
+                    let Some(tier) = data.extra.just_completed_tier.clone() else {
+                        debug!("COMBAT_ACHIEVEMENT event without a completed tier, dropping");
+                        return DinkStatus::Ignored;
+                    };
+                    sendable = true;
+
+                    let tier_name = title_case(&tier);
+                    let credited = match dink_achievement(&dink_handler, &format!("CA_{}", tier.to_uppercase()), "", &discord_id, &data.player_name, member.display_name()).await {
+                        Ok(recorded) => recorded,
+                        Err(e) => {
+                            error!("Failed to record Dink achievement: {:?}", e);
+                            return DinkStatus::Failed;
+                        }
+                    };
+                    embed = embed.description(format!("Completed every **{}** combat achievement!", tier_name));
+                    if let (Some(total_points), Some(possible)) = (data.extra.total_points, data.extra.total_possible_points) {
+                        embed = embed.field("CA Points", format_value(format!("{}/{}", total_points, possible)), true);
+                    }
+                    embed = field_if_exists(embed, data.extra.next_tier.as_deref().map(|tier| format_value(title_case(tier))), "Next Tier");
+                    embed = achievement_points_fields(embed, credited.map_or(0, |(points, _)| points), credited.map(|(_, total)| total));
+
+                    let _ = logger::log_action(
+                        &dink_handler.ctx,
+                        &discord_id,
+                        "DINK COMBAT ACHIEVEMENT",
+                        &format!("{} completed the {} combat achievement tier (+{} points)", data.player_name, tier_name, credited.map_or(0, |(points, _)| points))
+                    ).await;
+                }
+                "ACHIEVEMENT_DIARY" => {
+                    debug!("Received achievement diary");
+                    let (Some(area), Some(difficulty)) = (data.extra.area.clone(), data.extra.difficulty.clone()) else {
+                        debug!("ACHIEVEMENT_DIARY event without an area or difficulty, dropping");
+                        return DinkStatus::Ignored;
+                    };
+                    sendable = true;
+
+                    let difficulty_name = title_case(&difficulty);
+                    let credited = match dink_achievement(&dink_handler, &format!("DIARY_{}", difficulty.to_uppercase()), &area, &discord_id, &data.player_name, member.display_name()).await {
+                        Ok(recorded) => recorded,
+                        Err(e) => {
+                            error!("Failed to record Dink achievement: {:?}", e);
+                            return DinkStatus::Failed;
+                        }
+                    };
+                    embed = embed.description(format!("Completed the **{} {}** diary!", search_link(format!("{} Diary", area)), difficulty_name));
+                    if let (Some(tasks_completed), Some(tasks_total)) = (data.extra.tasks_completed, data.extra.tasks_total) {
+                        embed = embed.field("Tasks", format_value(format!("{}/{}", tasks_completed, tasks_total)), true);
+                    }
+                    embed = field_if_exists(embed, data.extra.total.map(|total| format_value(total.to_string())), "Diaries Completed");
+                    embed = achievement_points_fields(embed, credited.map_or(0, |(points, _)| points), credited.map(|(_, total)| total));
+
+                    let _ = logger::log_action(
+                        &dink_handler.ctx,
+                        &discord_id,
+                        "DINK DIARY",
+                        &format!("{} completed the {} {} diary (+{} points)", data.player_name, area, difficulty_name, credited.map_or(0, |(points, _)| points))
+                    ).await;
+                }
+                "KILL_COUNT" => {
+                    debug!("Received kill count");
+                    let (Some(boss), Some(count)) = (data.extra.boss.clone(), data.extra.count) else {
+                        debug!("KILL_COUNT event without a boss or count, dropping");
+                        return DinkStatus::Ignored;
+                    };
+                    //If this kill was a PB, time is the new PB; otherwise Dink may tell us the existing one
+                    let is_personal_best = data.extra.is_personal_best.unwrap_or(false);
+                    let personal_best_ms = if is_personal_best {
+                        data.extra.time.as_deref().and_then(utils::parse_iso_duration)
+                    } else {
+                        data.extra.personal_best.as_deref().and_then(utils::parse_iso_duration)
+                    };
+
+                    match record_kill_count(&dink_handler.db, &data, &discord_id, &boss, count, personal_best_ms).await {
+                        //Only a fresh PB on this kill can be a new clan record, not one we're just catching up on
+                        Ok(Some(previous)) if is_personal_best => {
+                            if let Some(time_ms) = personal_best_ms.filter(|ms| *ms < previous.personal_best_ms) {
+                                sendable = true;
+                                embed = embed.title("New Clan Record!")
+                                    .description(format!("Set a new clan record at {}!", search_link(boss.clone())))
+                                    .field("Time", format_value(utils::format_duration(time_ms)), true)
+                                    .field("Kill Count", format_value(count.to_string()), true)
+                                    .field("", "", false)
+                                    .field("Previous Record", format_value(format!("{} by {}", utils::format_duration(previous.personal_best_ms), previous.runescape_name)), true);
+
+                                let _ = logger::log_action(
+                                    &dink_handler.ctx,
+                                    &discord_id,
+                                    "DINK CLAN RECORD",
+                                    &format!("{} set a new clan record at {}: {} (previously {} by {})",
+                                        data.player_name, boss, utils::format_duration(time_ms), utils::format_duration(previous.personal_best_ms), previous.runescape_name)
+                                ).await;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            error!("Failed to record kill count: {:?}", e);
                         }
                     }
                 }
                 _ => {
                     debug!("Received type we don't handle");
+                    status = DinkStatus::Unsupported;
                     let _ = logger::log_action(
                         &dink_handler.ctx,
                         &member.user.id.to_string(),
@@ -407,7 +1024,7 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                 let builder = CreateMessage::new().add_embed(embed);
                 let Some(channel_id) = config.runelite_channel_id else {
                     error!("RUNELITE_CHANNEL_ID not configured, dropping Dink notification");
-                    return;
+                    return status;
                 };
                 let send_result = match screenshot {
                     Some(shot) => channel_id.send_files(&dink_handler.ctx.http, [shot], builder).await.map(|_| ()),
@@ -417,6 +1034,7 @@ async fn process_dink_event(dink_handler: DinkHandler, data: DinkPayload, dink_f
                     error!("Failed to send Dink notification: {:?}", why);
                 }
             }
+            status
         }
 }
 //This function is so if you want to change the formatting on everything, you can ("fix" makes the text blue)
@@ -497,79 +1115,281 @@ async fn identify_user (data: DinkPayload, db: SqlitePool, ctx: Context, guild_i
     member
 }
 /// Records a Dink collection log entry and awards points (if any) through
-/// `rank_manager::add_points`, instead of a raw `UPDATE users`. That gets us three things for
-/// free: the `users` row is upserted before anything references it (a raw `UPDATE` on a
-/// nonexistent row is a silent no-op - the previous code assumed the row already existed, which
-/// isn't true for a brand-new user's first-ever event), rank-up/down notifications fire the same
-/// way they do for the /clog command, and the caller can show the real post-event points total
-/// instead of whatever was on hand before this event.
-/// collection_log_entries.discord_id also has a foreign key on users.discord_id (enforced - sqlx
-/// enables PRAGMA foreign_keys by default), so the upsert has to happen before that insert too,
-/// or it fails silently right along with the points update.
-/// Returns (points awarded, user's new points total).
-async fn dink_clog(handler: &DinkHandler, item_id: i64, name: String, discord_id: String, user_name: &str) -> (i64, i64) {
-
-    let points = handler.collection_log_manager.calculate_points_dink(item_id).await.unwrap_or(0);
+/// `rank_manager::record_points`, in the same transaction as the entry - if the insert fails
+/// nothing is credited, and there's never an entry without its ledger row (or the other way
+/// round). The `users` row is upserted first: collection_log_entries.discord_id has a foreign
+/// key on users.discord_id (enforced - sqlx enables PRAGMA foreign_keys by default), and a
+/// brand-new user's first-ever event won't have one yet. Rank-up/down notifications then fire
+/// the same way they do for the /clog command, and the caller can show the real post-event
+/// points total instead of whatever was on hand before this event.
+/// Returns the user's new points total, None if they already have the item (there's a unique
+/// index on live entries per user and item), or an error if nothing could be recorded (the event
+/// is then marked failed, so it can be replayed).
+async fn dink_clog(handler: &DinkHandler, item_id: i64, name: String, points: i64, discord_id: String, user_name: &str) -> anyhow::Result<Option<i64>> {
+    let mut tx = handler.db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) VALUES (?, 0, 0) ON CONFLICT(discord_id) DO NOTHING",
+        discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
 
     // Record the collection log entry
-    let _ = sqlx::query!(
-        "INSERT INTO collection_log_entries (discord_id, item_name, points, item_id) VALUES (?, ?, ?, ?)",
+    let inserted = sqlx::query!(
+        "INSERT INTO collection_log_entries (discord_id, item_name, points, item_id, dink_inbox_id) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(discord_id, item_id) WHERE deleted_at IS NULL DO NOTHING",
         discord_id,
         name,
         points,
-        item_id
+        item_id,
+        handler.inbox_id
     )
-    .execute(&handler.db)
-    .await;
+    .execute(&mut *tx)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(None);
+    }
+    let entry_id = inserted.last_insert_rowid();
 
-    match rank_manager::add_points(&handler.ctx, &discord_id, user_name, points, &handler.db).await {
-        Ok(update) => (points, update.new_points),
-        Err(e) => {
-            error!("Failed to record points for Dink clog: {:?}", e);
-            (points, 0)
-        }
+    let source_ref = format!("clog #{}", entry_id);
+    let (old_points, new_points) = rank_manager::record_points(&mut tx, &discord_id, points, "dink_clog", Some(&source_ref)).await?;
+    tx.commit().await?;
+
+    // It's credited either way by now, so a failed announcement doesn't fail the event
+    if let Err(e) = rank_manager::finish_points_update(&handler.ctx, &discord_id, user_name, old_points, new_points, &handler.db).await {
+        error!("Failed to update ranks for Dink clog: {:?}", e);
     }
+    Ok(Some(new_points))
 }
-/// Records a Dink drop and increments total_drops (which the previous raw-SQL version never
-/// touched, unlike the /drop command - /stats and /leaderboard both read it directly), then
-/// awards points through `rank_manager::add_points` (see dink_clog for why).
-/// Returns the user's new points total.
-async fn dink_drop(handler: &DinkHandler, item_id: i64, name: String, value: i64, discord_id: String, user_name: &str) -> i64 {
+/// Records an achievement (a 99, the quest cape, a combat achievement tier or a diary) and awards
+/// whatever achievement_points says it's worth, in the same transaction (see dink_clog).
+/// Each achievement + detail can only be credited to someone once, so a reinstalled plugin or a
+/// replayed event doesn't pay out twice.
+/// Returns (points awarded, user's new points total), or None if it was already credited.
+async fn dink_achievement(handler: &DinkHandler, achievement: &str, detail: &str, discord_id: &str, runescape_name: &str, user_name: &str) -> anyhow::Result<Option<(i64, i64)>> {
+    let points = sqlx::query_scalar!("SELECT points FROM achievement_points WHERE achievement = ?", achievement)
+        .fetch_optional(&handler.db)
+        .await?
+        .unwrap_or(0);
+
+    // The achievement and its points go in together, so there's never one without the other
+    let mut tx = handler.db.begin().await?;
+
+    // achievements.discord_id references users, so the row has to exist before the insert
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) VALUES (?, 0, 0) ON CONFLICT(discord_id) DO NOTHING",
+        discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let inserted = sqlx::query!(
+        "INSERT INTO achievements (discord_id, achievement, detail, runescape_name, points) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(discord_id, achievement, detail) DO NOTHING",
+        discord_id,
+        achievement,
+        detail,
+        runescape_name,
+        points
+    )
+    .execute(&mut *tx)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        debug!("User {} already has achievement {} {}", discord_id, achievement, detail);
+        return Ok(None);
+    }
+
+    let source_ref = format!("achievement #{}", inserted.last_insert_rowid());
+    let (old_points, new_points) = rank_manager::record_points(&mut tx, discord_id, points, "dink_achievement", Some(&source_ref)).await?;
+    tx.commit().await?;
+
+    if let Err(e) = rank_manager::finish_points_update(&handler.ctx, discord_id, user_name, old_points, new_points, &handler.db).await {
+        error!("Failed to update ranks for Dink achievement: {:?}", e);
+    }
+    Ok(Some((points, new_points)))
+}
+/// The clan's best time at a boss, before a kill count update
+struct ClanRecord {
+    runescape_name: String,
+    personal_best_ms: i64,
+}
+
+/// Updates the account's kill count and personal best at a boss (a PB is only ever lowered, and
+/// the kill count never goes backwards, so out-of-order or replayed events can't undo anything).
+/// Returns the clan record as it stood before this update, if anyone had one.
+async fn record_kill_count(db: &SqlitePool, data: &DinkPayload, discord_id: &str, boss: &str, count: i32, personal_best_ms: Option<i64>) -> Result<Option<ClanRecord>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let previous = sqlx::query_as!(
+        ClanRecord,
+        r#"SELECT runescape_name, personal_best_ms as "personal_best_ms!"
+           FROM kill_counts
+           WHERE boss = ? AND personal_best_ms IS NOT NULL
+           ORDER BY personal_best_ms ASC, personal_best_at ASC
+           LIMIT 1"#,
+        boss
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO kill_counts (account_hash, runescape_name, discord_id, boss, kill_count, personal_best_ms, personal_best_at)
+         VALUES (?, ?, ?, ?, ?, ?, CASE WHEN ? IS NOT NULL THEN CURRENT_TIMESTAMP END)
+         ON CONFLICT(account_hash, boss) DO UPDATE SET
+             runescape_name = excluded.runescape_name,
+             discord_id = excluded.discord_id,
+             kill_count = MAX(kill_count, excluded.kill_count),
+             personal_best_at = CASE WHEN excluded.personal_best_ms < COALESCE(personal_best_ms, excluded.personal_best_ms + 1)
+                 THEN CURRENT_TIMESTAMP ELSE personal_best_at END,
+             personal_best_ms = MIN(COALESCE(personal_best_ms, excluded.personal_best_ms), COALESCE(excluded.personal_best_ms, personal_best_ms)),
+             updated_at = CURRENT_TIMESTAMP",
+        data.dink_account_hash,
+        data.player_name,
+        discord_id,
+        boss,
+        count,
+        personal_best_ms,
+        personal_best_ms
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(previous)
+}
+/// Records a Dink drop and increments total_drops by its quantity (which the previous raw-SQL
+/// version never touched, unlike the /drop command - /stats and /leaderboard both read it
+/// directly), with its points in the same transaction (see dink_clog for why).
+/// `value` is the whole stack's value, same as /drop stores it, and `price` what it was worked
+/// out from (flagged to the mods if the market price wasn't trusted).
+/// Returns the user's new points total, or an error if nothing could be recorded (see dink_clog).
+async fn dink_drop(handler: &DinkHandler, item: &DinkItem, value: i64, price: Option<&DropPrice>, points: i64, discord_id: String, user_name: &str) -> anyhow::Result<i64> {
+    let snapshot_id = price.and_then(|price| price.snapshot_id);
+    let mut tx = handler.db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) VALUES (?, 0, 0) ON CONFLICT(discord_id) DO NOTHING",
+        discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
 
     // Record the drop
-    let _ = sqlx::query!(
-        "INSERT INTO drops (discord_id, item_name, value, item_id) VALUES (?, ?, ?, ?)",
+    let drop_id = sqlx::query!(
+        "INSERT INTO drops (discord_id, item_name, value, quantity, item_id, points, price_snapshot_id, dink_inbox_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
         discord_id,
-        name,
+        item.name,
         value,
-        item_id
+        item.quantity,
+        item.id,
+        points,
+        snapshot_id,
+        handler.inbox_id
     )
-    .execute(&handler.db)
-    .await;
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
 
-    let points = value / 100_000;
-    match rank_manager::add_points(&handler.ctx, &discord_id, user_name, points, &handler.db).await {
-        Ok(update) => {
-            let _ = sqlx::query!(
-                "UPDATE users
-                SET total_drops = total_drops + 1
-                WHERE discord_id = ?",
-                discord_id
-            )
-            .execute(&handler.db)
-            .await;
-            update.new_points
-        },
-        Err(e) => {
-            error!("Failed to record points for Dink drop: {:?}", e);
-            0
+    sqlx::query!(
+        "UPDATE users
+        SET total_drops = total_drops + ?
+        WHERE discord_id = ?",
+        item.quantity,
+        discord_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let source_ref = format!("drop #{}", drop_id);
+    let (old_points, new_points) = rank_manager::record_points(&mut tx, &discord_id, points, "dink_drop", Some(&source_ref)).await?;
+    tx.commit().await?;
+
+    if let Some(price) = price {
+        if let Err(e) = logger::flag_drop_price(&handler.ctx, drop_id, &discord_id, item.quantity, &item.name, price).await {
+            error!("Failed to flag Dink drop price: {:?}", e);
         }
     }
+
+    if let Err(e) = rank_manager::finish_points_update(&handler.ctx, &discord_id, user_name, old_points, new_points, &handler.db).await {
+        error!("Failed to update ranks for Dink drop: {:?}", e);
+    }
+    Ok(new_points)
 }
 fn field_if_exists(embed: CreateEmbed, value: Option<String>, name: &str) -> CreateEmbed {
     if let Some(value) = value { embed.field(name, value, true) } else { embed }
 }
+//Dink sends tiers and difficulties in caps (e.g. GRANDMASTER)
+fn title_case(value: &str) -> String {
+    let lower = value.to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+//Achievements only show points if the admins have made them worth any (see /achievement_points)
+fn achievement_points_fields(embed: CreateEmbed, points: i64, new_total: Option<i64>) -> CreateEmbed {
+    match new_total {
+        Some(new_total) if points != 0 => embed.field("", "", false)
+            .field("Points Added", format_value(format!("+{}", points)), true)
+            .field("Points Total", format_value(new_total.to_string()), true),
+        _ => embed,
+    }
+}
 fn search_link(name: String) -> String {
     let link = format!("https://oldschool.runescape.wiki/w/Special:Search?search={}", name.clone().replace(" ", "%20"));
     format!("[{}]({})", name, link)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn payload(notif_type: &str, account_hash: &str, extra: serde_json::Value) -> DinkPayload {
+        serde_json::from_value(json!({
+            "type": notif_type,
+            "playerName": "Kitten",
+            "accountType": "NORMAL",
+            "seasonalWorld": false,
+            "dinkAccountHash": account_hash,
+            "extra": extra,
+        }))
+        .unwrap()
+    }
+
+    fn item(id: i64, quantity: i64) -> serde_json::Value {
+        json!({ "id": id, "quantity": quantity, "priceEach": 1, "name": "Item", "criteria": [] })
+    }
+
+    fn loot(account_hash: &str, items: Vec<serde_json::Value>, kill_count: i32) -> DinkPayload {
+        payload("LOOT", account_hash, json!({ "source": "Vorkath", "items": items, "killCount": kill_count }))
+    }
+
+    #[test]
+    fn item_order_doesnt_change_the_fingerprint() {
+        let first = loot("abc", vec![item(536, 2), item(1751, 1), item(11286, 1)], 100);
+        let second = loot("abc", vec![item(11286, 1), item(536, 2), item(1751, 1)], 100);
+        assert_eq!(dink_fingerprint(&first), dink_fingerprint(&second));
+    }
+
+    #[test]
+    fn skill_order_doesnt_change_the_fingerprint() {
+        let first = payload("LEVEL", "abc", json!({ "levelledSkills": { "Attack": 99, "Strength": 99, "Defence": 99 } }));
+        let second = payload("LEVEL", "abc", json!({ "levelledSkills": { "Defence": 99, "Attack": 99, "Strength": 99 } }));
+        assert_eq!(dink_fingerprint(&first), dink_fingerprint(&second));
+    }
+
+    #[test]
+    fn a_new_kill_count_is_a_new_event() {
+        let first = loot("abc", vec![item(11286, 1)], 100);
+        let second = loot("abc", vec![item(11286, 1)], 101);
+        assert_ne!(dink_fingerprint(&first), dink_fingerprint(&second));
+    }
+
+    #[test]
+    fn different_quantities_or_accounts_are_different_events() {
+        let first = loot("abc", vec![item(536, 2)], 100);
+        assert_ne!(dink_fingerprint(&first), dink_fingerprint(&loot("abc", vec![item(536, 3)], 100)));
+        assert_ne!(dink_fingerprint(&first), dink_fingerprint(&loot("def", vec![item(536, 2)], 100)));
+    }
+}
@@ -0,0 +1,109 @@
+// The points ledger on its own, without anything Discord - shared with the migrate utility (see
+// src/bin/migrate.rs), which has no bot to announce rank changes through
+use anyhow::Result;
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// Appends a row to points_ledger and brings users.points back in line with the ledger, on
+/// whatever connection/transaction it's handed. Writing first (the user upsert) means SQLite
+/// hands us the write lock before we read anything, so two events for the same user can't both
+/// read the same old total.
+/// Returns (old points, new points).
+pub async fn record_points(
+    conn: &mut SqliteConnection,
+    discord_id: &str,
+    amount: i64,
+    reason: &str,
+    source_ref: Option<&str>,
+) -> Result<(i64, i64)> {
+    // Insert or update user
+    sqlx::query!(
+        "INSERT INTO users (discord_id, points, total_drops) 
+         VALUES (?, 0, 0)
+         ON CONFLICT(discord_id) DO NOTHING",
+        discord_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Get current points before update
+    let old_points = sqlx::query!(
+        "SELECT points FROM users WHERE discord_id = ?",
+        discord_id
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .points;
+
+    sqlx::query!(
+        "INSERT INTO points_ledger (discord_id, amount, reason, source_ref) VALUES (?, ?, ?, ?)",
+        discord_id,
+        amount,
+        reason,
+        source_ref
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // users.points is only ever the sum of the ledger
+    sqlx::query!(
+        "UPDATE users
+         SET points = (SELECT COALESCE(SUM(amount), 0) FROM points_ledger WHERE discord_id = ?)
+         WHERE discord_id = ?",
+        discord_id,
+        discord_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let new_points = sqlx::query!(
+        "SELECT points FROM users WHERE discord_id = ?",
+        discord_id
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .points;
+
+    Ok((old_points, new_points))
+}
+
+/// A user whose stored points don't match what their drops, clogs, achievements and adjustments
+/// add up to
+pub struct PointsDiscrepancy {
+    pub discord_id: String,
+    pub stored_points: i64,
+    pub drop_points: i64,
+    pub clog_points: i64,
+    pub achievement_points: i64,
+    pub adjustment_points: i64,
+    pub expected_points: i64,
+}
+
+/// Compares everyone's users.points against the total v_users recomputes from their drops,
+/// collection log entries, achievements and mod adjustments, and returns everyone who's off, biggest difference first
+pub async fn audit_points(db: &SqlitePool) -> Result<Vec<PointsDiscrepancy>> {
+    Ok(sqlx::query!(
+        r#"SELECT users.discord_id as "discord_id!", users.points,
+                  COALESCE(v_users.drop_points, 0) as "drop_points!: i64",
+                  COALESCE(v_users.clog_points, 0) as "clog_points!: i64",
+                  COALESCE(v_users.achievement_points, 0) as "achievement_points!: i64",
+                  COALESCE(v_users.adjustment_points, 0) as "adjustment_points!: i64",
+                  v_users.total_points as "total_points!: i64"
+           FROM users
+           JOIN v_users ON v_users.discord_id = users.discord_id
+           WHERE users.points != v_users.total_points
+           ORDER BY ABS(users.points - v_users.total_points) DESC"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| PointsDiscrepancy {
+        discord_id: row.discord_id,
+        stored_points: row.points,
+        drop_points: row.drop_points,
+        clog_points: row.clog_points,
+        achievement_points: row.achievement_points,
+        adjustment_points: row.adjustment_points,
+        expected_points: row.total_points,
+    })
+    .collect())
+}
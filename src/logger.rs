@@ -1,7 +1,10 @@
 use anyhow::Result;
 use serenity::prelude::*;
 use serenity::model::id::UserId;
+use serenity::all::{Attachment, CreateAttachment, CreateMessage};
 use crate::config::ConfigKey;
+use crate::command_handler::utils::format_gp;
+use crate::prices::DropPrice;
 
 /// Logs an action to the bot's log channel
 pub async fn log_action(
@@ -33,8 +36,10 @@ pub async fn log_action(
         );
         
         // Send to log channel
-        if let Err(why) = config.log_channel_id.say(&ctx.http, log_message).await {
-            tracing::error!("Failed to send log message: {:?}", why);
+        for chunk in split_message(&log_message) {
+            if let Err(why) = config.log_channel_id.say(&ctx.http, chunk).await {
+                tracing::error!("Failed to send log message: {:?}", why);
+            }
         }
     }
     
@@ -56,9 +61,84 @@ pub async fn log_generic(
         );
 
         // Send to log channel
-        if let Err(why) = config.log_channel_id.say(&ctx.http, log_message).await {
-            tracing::error!("Failed to send log message: {:?}", why);
+        for chunk in split_message(&log_message) {
+            if let Err(why) = config.log_channel_id.say(&ctx.http, chunk).await {
+                tracing::error!("Failed to send log message: {:?}", why);
+            }
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Re-uploads a submission's screenshot to the log channel, so it outlives the message it came
+/// with, and returns a link to it
+pub async fn log_evidence(
+    ctx: &Context,
+    user_id: &str,
+    description: &str,
+    attachment: &Attachment,
+) -> Result<Option<String>> {
+    let data = ctx.data.read().await;
+    let Some(config) = data.get::<ConfigKey>() else {
+        return Ok(None);
+    };
+    let file = CreateAttachment::bytes(attachment.download().await?, attachment.filename.clone());
+    let message = config.log_channel_id
+        .send_message(&ctx.http, CreateMessage::new()
+            .content(format!("📷 Screenshot from <@{}> for {}", user_id, description))
+            .add_file(file))
+        .await?;
+    Ok(Some(message.link()))
+}
+
+/// Tells the mod channel about a drop whose market price wasn't trusted (see
+/// `PriceManager::get_drop_price`), so someone can check it and set a price override if needed.
+/// Does nothing for drops priced normally.
+pub async fn flag_drop_price(
+    ctx: &Context,
+    drop_id: i64,
+    discord_id: &str,
+    quantity: i64,
+    item_name: &str,
+    price: &DropPrice,
+) -> Result<()> {
+    let Some(flag) = &price.flag else {
+        return Ok(());
+    };
+    let data = ctx.data.read().await;
+    if let Some(config) = data.get::<ConfigKey>() {
+        let message = format!(
+            "⚠️ **Suspicious price** on drop #{}: <@{}> got {}x {} - {}. Valued at {} each instead of the market's {}. Check it with `/price_audit {}`, or set a price with `/price_override`.",
+            drop_id,
+            discord_id,
+            quantity,
+            item_name,
+            flag.reason,
+            format_gp(price.price_each),
+            format_gp(flag.market_price),
+            drop_id
+        );
+        config.mod_channel_id.say(&ctx.http, message).await?;
+    }
+    Ok(())
+}
+
+// Discord rejects messages over 2000 characters, and some logs (recalculations, audits) list a
+// line per user, so split on line breaks to keep each piece under the limit
+fn split_message(message: &str) -> Vec<String> {
+    const LIMIT: usize = 1900;
+    let mut chunks = vec![String::new()];
+    for line in message.lines() {
+        let current = chunks.last_mut().unwrap();
+        if !current.is_empty() && current.len() + line.len() + 1 > LIMIT {
+            chunks.push(String::new());
+        }
+        let current = chunks.last_mut().unwrap();
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        // A single line that's too long on its own just gets cut
+        current.push_str(&line.chars().take(LIMIT).collect::<String>());
+    }
+    chunks
+}
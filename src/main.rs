@@ -3,9 +3,11 @@ mod prices;
 mod collection_log;
 mod config;
 mod rank_manager;
+mod ledger;
 mod logger;
 mod runescape_tracker;
 mod dink_listener;
+mod scoring;
 
 use anyhow::Result;
 use axum::Extension;
@@ -36,7 +38,7 @@ struct Handler {
 }
 
 #[derive(Clone)]
-struct DinkHandler {
+pub struct DinkHandler {
     db: SqlitePool,
     price_manager: Arc<prices::PriceManager>,
     collection_log_manager: Arc<collection_log::CollectionLogManager>,
@@ -44,6 +46,9 @@ struct DinkHandler {
     ctx: Context,
     guild_id: GuildId,
     config: Config,
+    // The dink_inbox entry being handled - set per event, so whatever it credits can be traced
+    // back to it (see replay_dink_event)
+    inbox_id: Option<i64>,
 }
 
 #[async_trait]
@@ -117,7 +122,36 @@ impl EventHandler for Handler {
             ctx,
             guild_id,
             config,
+            inbox_id: None,
         };
+        // /dink_replay needs to run events back through the same handler
+        {
+            let mut data = handler.ctx.data.write().await;
+            data.insert::<dink_listener::DinkHandlerKey>(handler.clone());
+        }
+        // Replays can credit an event again, so they get their own listener that's only reachable
+        // from this machine and needs its own token - every player has the webhook token
+        match handler.config.dink_replay_token.clone() {
+            Some(_) => {
+                let replay_handler = handler.clone();
+                let replay_port = handler.config.dink_replay_port;
+                tokio::spawn(async move {
+                    let app = Router::new().route("/dink/replay/{id}", post(dink_listener::dink_replay_handler))
+                        .layer(Extension(replay_handler));
+                    let listener = match tokio::net::TcpListener::bind(("127.0.0.1", replay_port)).await {
+                        Ok(listener) => listener,
+                        Err(why) => {
+                            error!("Couldn't start the Dink replay listener on port {}: {:?}", replay_port, why);
+                            return;
+                        }
+                    };
+                    if let Err(why) = axum::serve(listener, app).into_future().await {
+                        error!("Err with replay server: {:?}", why);
+                    }
+                });
+            }
+            None => info!("DINK_REPLAY_TOKEN isn't set - the dink_replay CLI won't be able to reach the bot"),
+        }
         tokio::spawn(async move {
             // build our application with a single route, gated by a shared secret in the path -
             // Dink can't send custom headers, so the token has to live in the URL itself
@@ -162,10 +196,16 @@ async fn main() -> Result<()> {
     sqlx::migrate!().run(&db).await?;
 
     // Initialize managers
-    let price_manager = Arc::new(prices::PriceManager::new().await?);
+    let price_manager = Arc::new(prices::PriceManager::new(&db).await?);
     let collection_log_manager = Arc::new(collection_log::CollectionLogManager::new(&db).await?);
     let runescape_tracker = Arc::new(runescape_tracker::RunescapeTracker::new().await?);
 
+    match price_manager.backfill_drop_item_ids(&db).await {
+        Ok(0) => {}
+        Ok(updated) => info!("Matched {} drops to their item id", updated),
+        Err(e) => error!("Failed to backfill drop item ids: {}", e),
+    }
+
     // Create a new instance of the client
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT | GatewayIntents::GUILD_MESSAGES;
     let mut client = Client::builder(&token, intents)
@@ -1,11 +1,130 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use levenshtein::levenshtein;
 use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{error, info, debug};
+use tracing::{error, info, debug, warn};
 
 const USER_AGENT: &str = "KittyScape Loot Bot/1.0";
+// Rows per INSERT when saving the cache, well under SQLite's bound parameter limit
+const CACHE_CHUNK_SIZE: usize = 500;
+// How many hours of snapshots a drop's price is averaged over
+pub const TWAP_HOURS: i64 = 6;
+// Snapshots no drop (or pending submission) points at are thrown away after this long
+const SNAPSHOT_RETENTION_DAYS: i64 = 30;
+// Fewer items than this traded over TWAP_HOURS and the price is too easy to push around
+const MIN_TRADE_VOLUME: i64 = 10;
+// Buy price more than this many times the sell price means nobody actually agrees on a price
+const MAX_SPREAD_RATIO: f64 = 2.0;
+
+// What people actually call things, mapped to the wiki's name. Keys are in normalize_name form.
+const ITEM_ALIASES: &[(&str, &str)] = &[
+    ("tbow", "Twisted bow"),
+    ("dwh", "Dragon warhammer"),
+    ("dhcb", "Dragon hunter crossbow"),
+    ("dhl", "Dragon hunter lance"),
+    ("zcb", "Zaryte crossbow"),
+    ("acb", "Armadyl crossbow"),
+    ("rcb", "Rune crossbow"),
+    ("ags", "Armadyl godsword"),
+    ("bgs", "Bandos godsword"),
+    ("sgs", "Saradomin godsword"),
+    ("zgs", "Zamorak godsword"),
+    ("dfs", "Dragonfire shield"),
+    ("dds", "Dragon dagger"),
+    ("claws", "Dragon claws"),
+    ("dclaws", "Dragon claws"),
+    ("dpick", "Dragon pickaxe"),
+    ("dbow", "Dark bow"),
+    ("whip", "Abyssal whip"),
+    ("tent", "Abyssal tentacle"),
+    ("bp", "Toxic blowpipe (empty)"),
+    ("blowpipe", "Toxic blowpipe (empty)"),
+    ("sotd", "Staff of the dead"),
+    ("tsotd", "Toxic staff (uncharged)"),
+    ("scythe", "Scythe of vitur (uncharged)"),
+    ("sang", "Sanguinesti staff (uncharged)"),
+    ("shadow", "Tumeken's shadow (uncharged)"),
+    ("fang", "Osmumten's fang"),
+    ("ely", "Elysian spirit shield"),
+    ("arcane", "Arcane spirit shield"),
+    ("spectral", "Spectral spirit shield"),
+    ("kodai", "Kodai wand"),
+    ("tassets", "Bandos tassets"),
+    ("bcp", "Bandos chestplate"),
+    ("fury", "Amulet of fury"),
+    ("torture", "Amulet of torture"),
+    ("anguish", "Necklace of anguish"),
+    ("tormented", "Tormented bracelet"),
+    ("occult", "Occult necklace"),
+    ("prims", "Primordial boots"),
+    ("pegs", "Pegasian boots"),
+    ("eternals", "Eternal boots"),
+    ("vw", "Voidwaker"),
+];
+
+/// An item name reduced to what people reliably type: lowercase, no apostrophes, and any other
+/// punctuation (brackets, hyphens) as single spaces, so "Tumeken's Shadow" matches "tumekens shadow"
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| *c != '\'')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// How many typos to forgive in a name this long
+fn typo_allowance(name: &str) -> usize {
+    (name.len() / 5).clamp(1, 3)
+}
+
+// resolve_item_name against a given set of mappings
+fn resolve_name(mappings: &HashMap<String, ItemMapping>, input: &str) -> Option<String> {
+    if mappings.contains_key(input) {
+        return Some(input.to_string());
+    }
+
+    let wanted = normalize_name(input);
+    if wanted.is_empty() {
+        return None;
+    }
+    if let Some(mapping) = mappings.values().find(|mapping| normalize_name(&mapping.name) == wanted) {
+        return Some(mapping.name.clone());
+    }
+    if let Some((_, name)) = ITEM_ALIASES.iter().find(|(alias, _)| *alias == wanted) {
+        if mappings.contains_key(*name) {
+            return Some(name.to_string());
+        }
+    }
+
+    let mut best: Option<(usize, &str)> = None;
+    let mut tied = false;
+    for mapping in mappings.values() {
+        let distance = levenshtein(&wanted, &normalize_name(&mapping.name));
+        if distance > typo_allowance(&wanted) {
+            continue;
+        }
+        match best {
+            Some((best_distance, _)) if distance > best_distance => {}
+            Some((best_distance, _)) if distance == best_distance => tied = true,
+            _ => {
+                best = Some((distance, &mapping.name));
+                tied = false;
+            }
+        }
+    }
+    if tied {
+        None
+    } else {
+        best.map(|(_, name)| name.to_string())
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ItemMapping {
@@ -35,47 +154,212 @@ pub struct ItemPrice {
     pub low_time: Option<i64>,
 }
 
+/// One item's entry in the /1h feed: average prices and volumes over the hour
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HourlyPrices {
+    pub data: HashMap<String, HourlyPrice>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HourlyPrice {
+    pub avg_high_price: Option<f64>,
+    pub high_price_volume: Option<i64>,
+    pub avg_low_price: Option<f64>,
+    pub low_price_volume: Option<i64>,
+}
+
+/// What a drop is valued at per item, and the newest snapshot that went into it (if any did)
+#[derive(Debug, Clone)]
+pub struct DropPrice {
+    pub price_each: i64,
+    pub snapshot_id: Option<i64>,
+    // Set by hand in price_overrides rather than taken from the market
+    pub overridden: bool,
+    pub flag: Option<PriceFlag>,
+}
+
+/// Why the market price wasn't trusted for a drop, and what it would have been
+#[derive(Debug, Clone)]
+pub struct PriceFlag {
+    pub reason: String,
+    pub market_price: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct PriceData {
     pub mappings: HashMap<String, ItemMapping>,
     pub latest_prices: HashMap<i64, ItemPrice>,
+    // When latest_prices was last fetched from the wiki, or when the cached copy was if we started from that
+    pub prices_updated_at: Option<DateTime<Utc>>,
 }
 
 pub struct PriceManager {
     data: Arc<RwLock<PriceData>>,
     client: reqwest::Client,
+    db: SqlitePool,
 }
 
 impl PriceManager {
-    pub async fn new() -> Result<Self> {
+    // The wiki being down shouldn't stop the bot starting - both the mapping and prices fall back to
+    // the copy saved in the database the last time they were fetched
+    pub async fn new(db: &SqlitePool) -> Result<Self> {
         let client = reqwest::Client::builder()
             .user_agent(USER_AGENT)
             .build()?;
 
-        let mappings = Self::fetch_mappings(&client).await?;
-        info!("PriceManager initialized with {} items", mappings.len());
-        
-        // Debug log some example items
-        for (name, mapping) in mappings.iter().take(5) {
-            debug!("Example price item: {} (ID: {})", name, mapping.id);
-        }
-
         let data = PriceData {
-            mappings,
+            mappings: HashMap::new(),
             latest_prices: HashMap::new(),
+            prices_updated_at: None,
         };
 
         let manager = Self {
             data: Arc::new(RwLock::new(data)),
             client,
+            db: db.clone(),
         };
 
+        if let Err(e) = manager.refresh_mappings().await {
+            warn!("Failed to fetch item mappings, using the cached copy: {}", e);
+            manager.load_cached_mappings().await?;
+        }
+
+        {
+            let data = manager.data.read().await;
+            info!("PriceManager initialized with {} items", data.mappings.len());
+
+            // Debug log some example items
+            for (name, mapping) in data.mappings.iter().take(5) {
+                debug!("Example price item: {} (ID: {})", name, mapping.id);
+            }
+        }
+
         // Do initial price update
-        manager.update_prices().await?;
+        if let Err(e) = manager.update_prices().await {
+            warn!("Failed to fetch latest prices, using the cached copy: {}", e);
+            manager.load_cached_prices().await?;
+        }
 
         Ok(manager)
     }
 
+    async fn refresh_mappings(&self) -> Result<()> {
+        let mappings = Self::fetch_mappings(&self.client).await?;
+        if let Err(e) = self.save_mappings(&mappings).await {
+            error!("Failed to cache item mappings: {}", e);
+        }
+        self.data.write().await.mappings = mappings;
+        Ok(())
+    }
+
+    async fn save_mappings(&self, mappings: &HashMap<String, ItemMapping>) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("DELETE FROM price_mappings").execute(&mut *tx).await?;
+
+        let items: Vec<&ItemMapping> = mappings.values().collect();
+        for chunk in items.chunks(CACHE_CHUNK_SIZE) {
+            let mut insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO price_mappings (item_id, name, examine, members, low_alch, high_alch, buy_limit, value, icon) "
+            );
+            insert.push_values(chunk, |mut row, item| {
+                row.push_bind(item.id)
+                    .push_bind(&item.name)
+                    .push_bind(&item.examine)
+                    .push_bind(item.members)
+                    .push_bind(item.low_alch)
+                    .push_bind(item.high_alch)
+                    .push_bind(item.limit)
+                    .push_bind(item.value)
+                    .push_bind(&item.icon);
+            });
+            insert.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn load_cached_mappings(&self) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"SELECT item_id as "item_id!", name, examine, members as "members: bool", low_alch, high_alch, buy_limit, value, icon
+               FROM price_mappings"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        if rows.is_empty() {
+            error!("No cached item mappings either - drops can't be priced until the wiki is reachable");
+        }
+
+        let mappings = rows.into_iter()
+            .map(|row| (row.name.clone(), ItemMapping {
+                examine: row.examine,
+                id: row.item_id,
+                members: row.members,
+                low_alch: row.low_alch,
+                limit: row.buy_limit,
+                value: row.value,
+                high_alch: row.high_alch,
+                icon: row.icon,
+                name: row.name,
+            }))
+            .collect();
+        self.data.write().await.mappings = mappings;
+        Ok(())
+    }
+
+    async fn save_prices(&self, prices: &HashMap<i64, ItemPrice>) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("DELETE FROM latest_prices").execute(&mut *tx).await?;
+
+        let items: Vec<(&i64, &ItemPrice)> = prices.iter().collect();
+        for chunk in items.chunks(CACHE_CHUNK_SIZE) {
+            let mut insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO latest_prices (item_id, high, high_time, low, low_time) "
+            );
+            insert.push_values(chunk, |mut row, (id, price)| {
+                row.push_bind(**id)
+                    .push_bind(price.high)
+                    .push_bind(price.high_time)
+                    .push_bind(price.low)
+                    .push_bind(price.low_time);
+            });
+            insert.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn load_cached_prices(&self) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"SELECT item_id as "item_id!", high, high_time, low, low_time, updated_at FROM latest_prices"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut data = self.data.write().await;
+        data.prices_updated_at = rows.iter().map(|row| row.updated_at.and_utc()).max();
+        data.latest_prices = rows.into_iter()
+            .map(|row| (row.item_id, ItemPrice {
+                high: row.high,
+                high_time: row.high_time,
+                low: row.low,
+                low_time: row.low_time,
+            }))
+            .collect();
+
+        info!("Loaded {} cached prices", data.latest_prices.len());
+        Ok(())
+    }
+
+    /// When the prices drops are being valued at were fetched, if there are any
+    pub async fn prices_updated_at(&self) -> Option<DateTime<Utc>> {
+        self.data.read().await.prices_updated_at
+    }
+
     async fn fetch_mappings(client: &reqwest::Client) -> Result<HashMap<String, ItemMapping>> {
         let response = client
             .get("https://prices.runescape.wiki/api/v2/osrs/mapping")
@@ -101,22 +385,174 @@ impl PriceManager {
             .json::<LatestPrices>()
             .await?;
 
-        let mut data = self.data.write().await;
-        data.latest_prices.clear();
+        let latest_prices: HashMap<i64, ItemPrice> = response.data
+            .into_iter()
+            .filter_map(|(id_str, price)| id_str.parse::<i64>().ok().map(|id| (id, price)))
+            .collect();
 
-        for (id_str, price) in response.data {
-            if let Ok(id) = id_str.parse::<i64>() {
-                data.latest_prices.insert(id, price);
-            }
+        if let Err(e) = self.save_prices(&latest_prices).await {
+            error!("Failed to cache latest prices: {}", e);
         }
 
+        let mut data = self.data.write().await;
+        data.latest_prices = latest_prices;
+        data.prices_updated_at = Some(Utc::now());
+
         info!("Updated prices for {} items", data.latest_prices.len());
         Ok(())
     }
 
+    /// Saves the last hour's average prices for every item, and clears out old snapshots nothing
+    /// refers to. Taking the same hour twice (e.g. after a restart) is a no-op.
+    pub async fn take_price_snapshot(&self) -> Result<()> {
+        let response = self.client
+            .get("https://prices.runescape.wiki/api/v2/osrs/1h")
+            .send()
+            .await?
+            .json::<HourlyPrices>()
+            .await?;
+
+        let snapshots: Vec<(i64, HourlyPrice)> = response.data
+            .into_iter()
+            .filter_map(|(id_str, price)| id_str.parse::<i64>().ok().map(|id| (id, price)))
+            .collect();
+
+        let mut tx = self.db.begin().await?;
+        for chunk in snapshots.chunks(CACHE_CHUNK_SIZE) {
+            let mut insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR IGNORE INTO price_snapshots (item_id, avg_high, avg_low, high_volume, low_volume, window_start) "
+            );
+            insert.push_values(chunk, |mut row, (id, price)| {
+                row.push_bind(*id)
+                    .push_bind(price.avg_high_price)
+                    .push_bind(price.avg_low_price)
+                    .push_bind(price.high_price_volume.unwrap_or(0))
+                    .push_bind(price.low_price_volume.unwrap_or(0))
+                    .push_bind(response.timestamp);
+            });
+            insert.build().execute(&mut *tx).await?;
+        }
+
+        let retention = format!("-{} days", SNAPSHOT_RETENTION_DAYS);
+        let pruned = sqlx::query!(
+            "DELETE FROM price_snapshots
+             WHERE taken_at < datetime('now', ?)
+             AND id NOT IN (SELECT price_snapshot_id FROM drops WHERE price_snapshot_id IS NOT NULL)
+             AND id NOT IN (SELECT price_snapshot_id FROM pending_submissions WHERE price_snapshot_id IS NOT NULL)",
+            retention
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+
+        info!("Took price snapshot of {} items (pruned {} old ones)", snapshots.len(), pruned);
+        Ok(())
+    }
+
+    /// The price a drop of this item is worth each: a price override if an admin has set one,
+    /// otherwise the average of the last TWAP_HOURS of hourly snapshots, or the latest price if
+    /// there aren't any (e.g. the bot's only just started). If that market price looks manipulable
+    /// (hardly any volume, or a huge gap between buy and sell) it's capped at high alch and flagged.
+    pub async fn get_drop_price(&self, item_id: i64) -> Option<DropPrice> {
+        match sqlx::query_scalar!("SELECT price FROM price_overrides WHERE item_id = ?", item_id)
+            .fetch_optional(&self.db)
+            .await
+        {
+            Ok(Some(price_each)) => return Some(DropPrice { price_each, snapshot_id: None, overridden: true, flag: None }),
+            Ok(None) => {}
+            Err(e) => error!("Failed to look up price override for item {}: {}", item_id, e),
+        }
+
+        let window = format!("-{} hours", TWAP_HOURS);
+        let twap = sqlx::query!(
+            r#"SELECT AVG(COALESCE(avg_low, avg_high)) as "price: f64", MAX(id) as "snapshot_id: i64",
+                      AVG(avg_high) as "avg_high: f64", AVG(avg_low) as "avg_low: f64",
+                      SUM(high_volume + low_volume) as "volume: i64"
+               FROM price_snapshots
+               WHERE item_id = ? AND taken_at >= datetime('now', ?) AND COALESCE(avg_low, avg_high) IS NOT NULL"#,
+            item_id,
+            window
+        )
+        .fetch_one(&self.db)
+        .await;
+
+        let (market_price, snapshot_id, flag) = match twap {
+            Ok(row) if row.price.is_some() && row.snapshot_id.is_some() => {
+                let volume = row.volume.unwrap_or(0);
+                let flag = if volume < MIN_TRADE_VOLUME {
+                    Some(format!("only {} traded in the last {} hours", volume, TWAP_HOURS))
+                } else {
+                    Self::spread_flag(row.avg_high, row.avg_low)
+                };
+                (row.price.unwrap_or(0.0).round() as i64, row.snapshot_id, flag)
+            }
+            Ok(_) => {
+                // No snapshots, so no volumes either - the spread is all we can check
+                let latest = self.data.read().await.latest_prices.get(&item_id).cloned();
+                let flag = latest.and_then(|price| Self::spread_flag(price.high, price.low));
+                (self.get_item_id_price(&item_id).await?, None, flag)
+            }
+            Err(e) => {
+                error!("Failed to average snapshots for item {}: {}", item_id, e);
+                (self.get_item_id_price(&item_id).await?, None, None)
+            }
+        };
+
+        let Some(reason) = flag else {
+            return Some(DropPrice { price_each: market_price, snapshot_id, overridden: false, flag: None });
+        };
+        // High alch is a floor the market can't be pushed below for long, so it's a safe value to fall
+        // back on; items that can't be alched keep the market price and rely on the mods checking the flag
+        let price_each = match self.get_high_alch(item_id).await {
+            Some(high_alch) => market_price.min(high_alch),
+            None => market_price,
+        };
+        Some(DropPrice {
+            price_each,
+            snapshot_id,
+            overridden: false,
+            flag: Some(PriceFlag { reason, market_price }),
+        })
+    }
+
+    fn spread_flag(high: Option<f64>, low: Option<f64>) -> Option<String> {
+        let (high, low) = (high?, low?);
+        if low > 0.0 && high / low > MAX_SPREAD_RATIO {
+            Some(format!("buy price is {:.1}x the sell price", high / low))
+        } else {
+            None
+        }
+    }
+
+    async fn get_high_alch(&self, item_id: i64) -> Option<i64> {
+        let data = self.data.read().await;
+        data.mappings
+            .values()
+            .find(|mapping| mapping.id == item_id)
+            .and_then(|mapping| mapping.high_alch)
+            .map(|high_alch| high_alch.round() as i64)
+    }
+
     pub async fn start_price_updates(self: Arc<Self>) {
+        let snapshot_manager = Arc::clone(&self);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = snapshot_manager.take_price_snapshot().await {
+                    error!("Failed to take price snapshot: {}", e);
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+            }
+        });
+
         tokio::spawn(async move {
             loop {
+                // Started without a mapping (the wiki was down and nothing was cached), so keep trying
+                if self.data.read().await.mappings.is_empty() {
+                    if let Err(e) = self.refresh_mappings().await {
+                        error!("Failed to fetch item mappings: {}", e);
+                    }
+                }
                 if let Err(e) = self.update_prices().await {
                     error!("Failed to update prices: {}", e);
                 }
@@ -125,32 +561,96 @@ impl PriceManager {
         });
     }
 
+    /// Item names for autocomplete, best matches first: the exact name or a nickname for it, then
+    /// names starting with what's been typed, names with a word starting with it, names containing
+    /// it anywhere, and finally names that start with it give or take a typo
     pub async fn get_item_suggestions(&self, partial: &str) -> Vec<String> {
         let data = self.data.read().await;
-        let partial = partial.to_lowercase();
-        
-        data.mappings
-            .keys()
-            .filter(|name| name.to_lowercase().contains(&partial))
+        let wanted = normalize_name(partial);
+
+        let mut ranked: Vec<(u8, usize, &str)> = data.mappings
+            .values()
+            .filter_map(|mapping| {
+                let name = normalize_name(&mapping.name);
+                let rank = if name == wanted {
+                    0
+                } else if name.starts_with(&wanted) {
+                    2
+                } else if name.split(' ').any(|word| word.starts_with(&wanted)) {
+                    3
+                } else if name.contains(&wanted) {
+                    4
+                } else if wanted.len() >= 4 {
+                    let prefix: String = name.chars().take(wanted.chars().count()).collect();
+                    if levenshtein(&wanted, &prefix) > typo_allowance(&wanted) {
+                        return None;
+                    }
+                    5
+                } else {
+                    return None;
+                };
+                Some((rank, mapping.name.len(), mapping.name.as_str()))
+            })
+            .collect();
+
+        for (alias, name) in ITEM_ALIASES {
+            if alias.starts_with(&wanted) && data.mappings.contains_key(*name) {
+                ranked.push((if *alias == wanted { 0 } else { 1 }, name.len(), name));
+            }
+        }
+
+        // Shortest names first within a rank, so "Twisted bow" comes before "Twisted bow (or)"
+        ranked.sort();
+        let mut seen = std::collections::HashSet::new();
+        ranked.into_iter()
+            .filter(|(_, _, name)| seen.insert(*name))
             .take(25)  // Discord has a limit of 25 choices
-            .cloned()
+            .map(|(_, _, name)| name.to_string())
             .collect()
     }
 
-    pub async fn get_item_price(&self, name: &str) -> Option<i64> {
+    /// The wiki's name for an item typed by hand (or picked from autocomplete): the exact name,
+    /// the same name ignoring case and punctuation, a nickname from ITEM_ALIASES, or failing all
+    /// that the one name within a couple of typos of it. None if nothing matches, or if two names
+    /// are equally close and there's no telling which was meant.
+    pub async fn resolve_item_name(&self, input: &str) -> Option<String> {
+        resolve_name(&self.data.read().await.mappings, input)
+    }
+
+    pub async fn get_item_id(&self, name: &str) -> Option<i64> {
         let data = self.data.read().await;
-        
-        // Find the item mapping
-        let mapping = data.mappings.get(name)?;
-        
-        // Get the latest price
-        let price = data.latest_prices.get(&mapping.id)?;
-        
-        // Use the lowest available price, defaulting to high alch value if available, or 0 if not
-        Some(price.low
-            .or(price.high)
-            .or(mapping.high_alch)
-            .unwrap_or(0.0).round() as i64)
+
+        if let Some(mapping) = data.mappings.get(name) {
+            return Some(mapping.id);
+        }
+        // Older drops weren't always typed with the wiki's capitalisation
+        data.mappings
+            .values()
+            .find(|mapping| mapping.name.eq_ignore_ascii_case(name))
+            .map(|mapping| mapping.id)
+    }
+
+    /// Gives every drop recorded without an item id (everything before ids were stored, that the
+    /// migration couldn't match) the id its name maps to. Returns how many drops were updated.
+    pub async fn backfill_drop_item_ids(&self, db: &SqlitePool) -> Result<u64> {
+        let names = sqlx::query_scalar!("SELECT DISTINCT item_name FROM drops WHERE item_id IS NULL")
+            .fetch_all(db)
+            .await?;
+
+        let mut updated = 0;
+        for name in names {
+            match self.get_item_id(&name).await {
+                Some(item_id) => {
+                    updated += sqlx::query!("UPDATE drops SET item_id = ? WHERE item_name = ? AND item_id IS NULL", item_id, name)
+                        .execute(db)
+                        .await?
+                        .rows_affected();
+                }
+                None => debug!("No item id for dropped item: {}", name),
+            }
+        }
+
+        Ok(updated)
     }
 
     pub async fn get_item_id_price(&self, id: &i64) -> Option<i64> {
@@ -165,4 +665,73 @@ impl PriceManager {
             .or(price.high)
             .unwrap_or(0.0).round() as i64)
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mappings(names: &[&str]) -> HashMap<String, ItemMapping> {
+        names.iter()
+            .enumerate()
+            .map(|(id, name)| (name.to_string(), ItemMapping {
+                examine: None,
+                id: id as i64,
+                members: None,
+                low_alch: None,
+                limit: None,
+                value: None,
+                high_alch: None,
+                icon: None,
+                name: name.to_string(),
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_name_drops_case_apostrophes_and_punctuation() {
+        assert_eq!(normalize_name("Tumeken's Shadow (uncharged)"), "tumekens shadow uncharged");
+        assert_eq!(normalize_name("  Dragon-claws  "), "dragon claws");
+        assert_eq!(normalize_name("Osmumten's fang"), normalize_name("osmumtens FANG"));
+    }
+
+    #[test]
+    fn resolves_exact_and_loosely_typed_names() {
+        let mappings = mappings(&["Tumeken's shadow (uncharged)", "Twisted bow"]);
+        assert_eq!(resolve_name(&mappings, "Twisted bow").as_deref(), Some("Twisted bow"));
+        assert_eq!(resolve_name(&mappings, "TWISTED BOW").as_deref(), Some("Twisted bow"));
+        assert_eq!(resolve_name(&mappings, "tumekens shadow uncharged").as_deref(), Some("Tumeken's shadow (uncharged)"));
+    }
+
+    #[test]
+    fn resolves_nicknames_for_items_that_exist() {
+        assert_eq!(resolve_name(&mappings(&["Twisted bow"]), "tbow").as_deref(), Some("Twisted bow"));
+        assert_eq!(resolve_name(&mappings(&["Abyssal whip"]), "tbow"), None);
+    }
+
+    #[test]
+    fn forgives_a_typo_or_two() {
+        let mappings = mappings(&["Twisted bow", "Dragon warhammer"]);
+        assert_eq!(resolve_name(&mappings, "twistd bow").as_deref(), Some("Twisted bow"));
+        assert_eq!(resolve_name(&mappings, "dragon wrahammer").as_deref(), Some("Dragon warhammer"));
+        assert_eq!(resolve_name(&mappings, "dragon sword"), None);
+    }
+
+    #[test]
+    fn equally_close_names_are_ambiguous() {
+        let mappings = mappings(&["Rune bar", "Rune bat"]);
+        assert_eq!(resolve_name(&mappings, "rune baz"), None);
+    }
+
+    #[test]
+    fn a_closer_name_beats_a_tie_whatever_order_they_come_in() {
+        // "aaaaaaaaaa" is 1 away from the first name and 2 away from both of the others
+        let mappings = mappings(&["aaaaaaaaab", "aaaaaaaacc", "aaaaaaaadd"]);
+        assert_eq!(resolve_name(&mappings, "aaaaaaaaaa").as_deref(), Some("aaaaaaaaab"));
+    }
+
+    #[test]
+    fn empty_input_matches_nothing() {
+        assert_eq!(resolve_name(&mappings(&["Twisted bow"]), " '- "), None);
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::Result;
+use std::collections::HashMap;
 use serenity::prelude::*;
 use serenity::model::prelude::*;
 use serenity::utils::MessageBuilder;
 use sqlx::SqlitePool;
+use tracing::{debug, warn};
 use crate::config::ConfigKey;
 use crate::command_handler::format_points;
 
+pub use crate::ledger::{audit_points, record_points};
+
 pub struct PointsUpdate {
     pub old_points: i64,
     pub new_points: i64,
@@ -13,46 +17,36 @@ pub struct PointsUpdate {
     pub crossed_ranks: Vec<String>,
 }
 
+/// Records points for a user in the ledger (see `record_points`), then sends out any rank
+/// change notifications.
+/// `reason` and `source_ref` end up on the points_ledger row, so every point can be traced back
+/// to what caused it.
 pub async fn add_points(
     ctx: &Context,
     discord_id: &str,
     user_name: &str,
     points_to_add: i64,
+    reason: &str,
+    source_ref: Option<&str>,
     db: &SqlitePool,
 ) -> Result<PointsUpdate> {
-    // Insert or update user
-    sqlx::query!(
-        "INSERT INTO users (discord_id, points, total_drops) 
-         VALUES (?, 0, 0)
-         ON CONFLICT(discord_id) DO NOTHING",
-        discord_id
-    )
-    .execute(db)
-    .await?;
+    let mut tx = db.begin().await?;
+    let (old_points, new_points) = record_points(&mut tx, discord_id, points_to_add, reason, source_ref).await?;
+    tx.commit().await?;
 
-    // Get current points before update
-    let old_points = sqlx::query!(
-        "SELECT points FROM users WHERE discord_id = ?",
-        discord_id
-    )
-    .fetch_one(db)
-    .await?
-    .points;
-
-    // Update user points
-    sqlx::query!(
-        "UPDATE users 
-         SET points = points + ?
-         WHERE discord_id = ?",
-        points_to_add,
-        discord_id
-    )
-    .execute(db)
-    .await?;
-
-    // Get new points total
-    let new_points = old_points + points_to_add;
+    finish_points_update(ctx, discord_id, user_name, old_points, new_points, db).await
+}
 
+/// The rest of add_points, for callers that record the points in their own transaction (so the
+/// entry and its ledger row land together) - announces any rank change once that's committed
+pub async fn finish_points_update(
+    ctx: &Context,
+    discord_id: &str,
+    user_name: &str,
+    old_points: i64,
+    new_points: i64,
+    db: &SqlitePool,
+) -> Result<PointsUpdate> {
     let crossed_ranks = notify_rank_transition(ctx, discord_id, user_name, old_points, new_points, db).await?;
 
     // Get next rank for progress message
@@ -75,6 +69,158 @@ pub async fn add_points(
     })
 }
 
+/// Used for perk ranks that don't have their own announcement text
+pub const DEFAULT_PERK_ANNOUNCEMENT: &str = "{user}, you've unlocked new perks!";
+
+/// Fills in a perk announcement - {user} becomes a mention of the user, {channel} a mention of
+/// the rank request channel (if one is configured)
+pub fn render_announcement(announcement: &str, user_id: UserId, rank_request_channel_id: Option<ChannelId>) -> String {
+    let channel = rank_request_channel_id
+        .map(|id| id.mention().to_string())
+        .unwrap_or_else(|| "the rank request channel".to_string());
+    announcement
+        .replace("{user}", &user_id.mention().to_string())
+        .replace("{channel}", &channel)
+}
+
+/// Whether a user's rank roles could be brought in line automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleSync {
+    /// Roles were updated (or were already correct)
+    Applied,
+    /// The bot can't touch the roles (no Manage Roles, role above the bot, role doesn't exist),
+    /// so the mods have to do it by hand
+    NeedsMod,
+}
+
+// The bot only ever lives in one server (see cache_ready in main.rs), so that's the one whose
+// roles we manage
+fn home_guild(ctx: &Context) -> Option<GuildId> {
+    ctx.cache.guilds().first().copied()
+}
+
+/// Gives the user the role for the rank their points put them at, and takes away every other
+/// rank role they have, so only the current rank is ever held (and none at all below the lowest
+/// rank).
+/// Role names come straight from rank_thresholds.role_name and have to match the guild's role
+/// names exactly. `retired_roles` are rank roles that have just been removed or renamed in
+/// rank_thresholds, which are taken away too even though no threshold points at them anymore.
+pub async fn sync_rank_role(
+    ctx: &Context,
+    discord_id: &str,
+    points: i64,
+    retired_roles: &[String],
+    db: &SqlitePool,
+) -> Result<RoleSync> {
+    let Some(guild_id) = home_guild(ctx) else {
+        warn!("Not in a guild, can't sync rank roles for {}", discord_id);
+        return Ok(RoleSync::NeedsMod);
+    };
+    let user_id = UserId::new(discord_id.parse()?);
+
+    let ranks = sqlx::query!(
+        "SELECT points, role_name FROM rank_thresholds ORDER BY points ASC"
+    )
+    .fetch_all(db)
+    .await?;
+
+    // The rank they should be holding right now, if they've made any
+    let target_rank = ranks.iter().rev().find(|r| r.points <= points);
+
+    let guild_roles = guild_id.roles(&ctx.http).await?;
+    let roles_by_name: HashMap<&str, &Role> = guild_roles.values()
+        .map(|role| (role.name.as_str(), role))
+        .collect();
+
+    let target_role = match target_rank {
+        Some(target_rank) => match roles_by_name.get(target_rank.role_name.as_str()) {
+            Some(role) => Some(*role),
+            None => {
+                warn!("No role named '{}' in the server, can't assign it to {}", target_rank.role_name, discord_id);
+                return Ok(RoleSync::NeedsMod);
+            }
+        },
+        None => None,
+    };
+    let rank_roles: Vec<&Role> = ranks.iter()
+        .map(|r| r.role_name.as_str())
+        .chain(retired_roles.iter().map(String::as_str))
+        .filter_map(|name| roles_by_name.get(name).copied())
+        .collect();
+
+    if !can_manage_roles(ctx, guild_id, &guild_roles, &rank_roles).await {
+        return Ok(RoleSync::NeedsMod);
+    }
+
+    let member = ctx.http.get_member(guild_id, user_id).await?;
+
+    let superseded: Vec<RoleId> = rank_roles.iter()
+        .map(|role| role.id)
+        .filter(|id| Some(*id) != target_role.map(|role| role.id) && member.roles.contains(id))
+        .collect();
+
+    if let Some(target_role) = target_role {
+        if !member.roles.contains(&target_role.id) {
+            if let Err(why) = member.add_role(&ctx.http, target_role.id).await {
+                warn!("Failed to add {} role to {}: {:?}", target_role.name, discord_id, why);
+                return Ok(RoleSync::NeedsMod);
+            }
+        }
+    }
+    if !superseded.is_empty() {
+        if let Err(why) = member.remove_roles(&ctx.http, &superseded).await {
+            warn!("Failed to remove superseded rank roles from {}: {:?}", discord_id, why);
+            return Ok(RoleSync::NeedsMod);
+        }
+    }
+
+    debug!(
+        "Synced rank role for {}: {} (removed {} old roles)",
+        discord_id,
+        target_role.map_or("no rank", |role| role.name.as_str()),
+        superseded.len()
+    );
+    Ok(RoleSync::Applied)
+}
+
+// Discord only lets us hand out roles with Manage Roles, and only ones that sit below our own
+// highest role, so check both up front rather than finding out halfway through
+async fn can_manage_roles(
+    ctx: &Context,
+    guild_id: GuildId,
+    guild_roles: &HashMap<RoleId, Role>,
+    rank_roles: &[&Role],
+) -> bool {
+    let bot_id = ctx.cache.current_user().id;
+    let bot_member = match ctx.http.get_member(guild_id, bot_id).await {
+        Ok(member) => member,
+        Err(why) => {
+            warn!("Couldn't look up the bot's own member: {:?}", why);
+            return false;
+        }
+    };
+
+    // @everyone shares its id with the guild
+    let mut permissions = guild_roles.get(&RoleId::new(guild_id.get()))
+        .map(|role| role.permissions)
+        .unwrap_or_else(Permissions::empty);
+    let mut highest_position = 0;
+    for role in bot_member.roles.iter().filter_map(|id| guild_roles.get(id)) {
+        permissions |= role.permissions;
+        highest_position = highest_position.max(role.position);
+    }
+
+    if !permissions.intersects(Permissions::MANAGE_ROLES | Permissions::ADMINISTRATOR) {
+        warn!("Missing Manage Roles, rank roles will have to be handed out by the mods");
+        return false;
+    }
+    if let Some(role) = rank_roles.iter().find(|role| role.position >= highest_position) {
+        warn!("The {} role sits above the bot's highest role, rank roles will have to be handed out by the mods", role.name);
+        return false;
+    }
+    true
+}
+
 pub async fn notify_rank_transition(
     ctx: &Context,
     discord_id: &str,
@@ -90,7 +236,7 @@ pub async fn notify_rank_transition(
     // If adding points, check if any rank thresholds were crossed (ranked up)
     if new_points > old_points {
         let ranks_crossed = sqlx::query!(
-            "SELECT points, role_name FROM rank_thresholds 
+            "SELECT points, role_name, unlocks_perks, announcement FROM rank_thresholds 
              WHERE points > ? AND points <= ?
              ORDER BY points ASC",
             old_points,
@@ -106,6 +252,10 @@ pub async fn notify_rank_transition(
             
         // Send notification if ranks were crossed
         if !crossed_ranks.is_empty() {
+            let role_sync = sync_rank_role(ctx, discord_id, new_points, &[], db).await.unwrap_or_else(|why| {
+                tracing::error!("Failed to sync rank roles for {}: {:?}", discord_id, why);
+                RoleSync::NeedsMod
+            });
             let data = ctx.data.read().await;
             if let Some(config) = data.get::<ConfigKey>() {
                 // Format ranks list
@@ -122,45 +272,48 @@ pub async fn notify_rank_transition(
                     }
                 };
 
-                // Create notification with all crossed ranks
-                let notification = if ranks_crossed.len() == 1 {
-                    format!(
-                        "🎉 **Rank Up Alert!**\n{} has reached {} and is ready for the {} role!",
-                        user_name,
-                        format_points(new_points),
-                        ranks_text
-                    )
-                } else {
-                    format!(
-                        "🎉 **Multiple Rank Up Alert!**\n{} has reached {} and is ready for the following roles: {}!",
-                        user_name,
-                        format_points(new_points),
-                        ranks_text
-                    )
-                };
-
-                let mut rank_up_message = MessageBuilder::new();
+                // The bot hands out the roles itself when it can - the mods only need telling when it can't
+                if role_sync == RoleSync::NeedsMod {
+                    let notification = if ranks_crossed.len() == 1 {
+                        format!(
+                            "🎉 **Rank Up Alert!**\n{} has reached {} and is ready for the {} role!",
+                            user_name,
+                            format_points(new_points),
+                            ranks_text
+                        )
+                    } else {
+                        format!(
+                            "🎉 **Multiple Rank Up Alert!**\n{} has reached {} and is ready for the following roles: {}!",
+                            user_name,
+                            format_points(new_points),
+                            ranks_text
+                        )
+                    };
 
-                rank_up_message.push(notification);
+                    if let Err(why) = config.mod_channel_id
+                        .say(&ctx.http, notification)
+                        .await
+                    {
+                        tracing::error!("Failed to send rank up notification: {:?}", why);
+                    }
+                }
 
                 let discord_user_id = UserId::new(discord_id.parse().unwrap());
 
-                if let Some(rank_request_channel_id) = config.rank_request_channel_id {
-                    //If rank request channel exists and we hit the threshold rank this level up
-                    if ranks_text.contains("Meowster") { //This should technically be a configurable variable but that can come later
-                        rank_up_message.push("\n")
-                        .mention(&discord_user_id)
-                        .push(", you may now select any rank icon you want in ")
-                        .mention(&rank_request_channel_id)
-                        .push("!");
+                // Only ranks flagged as unlocking perks get announced, once each. They're for the
+                // member, so they go to the rank request channel when there is one
+                let announcements: Vec<String> = ranks_crossed.iter()
+                    .filter(|r| r.unlocks_perks != 0)
+                    .map(|rank| {
+                        let announcement = rank.announcement.as_deref().unwrap_or(DEFAULT_PERK_ANNOUNCEMENT);
+                        render_announcement(announcement, discord_user_id, config.rank_request_channel_id)
+                    })
+                    .collect();
+                if !announcements.is_empty() {
+                    let channel_id = config.rank_request_channel_id.unwrap_or(config.mod_channel_id);
+                    if let Err(why) = channel_id.say(&ctx.http, announcements.join("\n")).await {
+                        tracing::error!("Failed to send perk announcement: {:?}", why);
                     }
-                };
-
-                if let Err(why) = config.mod_channel_id
-                    .say(&ctx.http, rank_up_message.build())
-                    .await
-                {
-                    tracing::error!("Failed to send rank up notification: {:?}", why);
                 }
             }
         }
@@ -184,6 +337,10 @@ pub async fn notify_rank_transition(
             
         // Send notification if ranks were lost
         if !crossed_ranks.is_empty() {
+            let role_sync = sync_rank_role(ctx, discord_id, new_points, &[], db).await.unwrap_or_else(|why| {
+                tracing::error!("Failed to sync rank roles for {}: {:?}", discord_id, why);
+                RoleSync::NeedsMod
+            });
             let data = ctx.data.read().await;
             if let Some(config) = data.get::<ConfigKey>() {
                 // Format ranks list
@@ -201,7 +358,7 @@ pub async fn notify_rank_transition(
                 };
 
                 // Create notification about lost ranks
-                let notification = if ranks_lost.len() == 1 {
+                let mut notification = if ranks_lost.len() == 1 {
                     format!(
                         "⬇️ **Rank Down Alert!**\n{} is now at {} and has lost the {} role.",
                         user_name,
@@ -216,6 +373,9 @@ pub async fn notify_rank_transition(
                         ranks_text
                     )
                 };
+                if role_sync == RoleSync::Applied {
+                    notification.push_str(" Their roles have been updated.");
+                }
 
                 if let Err(why) = config.mod_channel_id
                     .say(&ctx.http, notification)
@@ -228,4 +388,102 @@ pub async fn notify_rank_transition(
     }
 
     Ok(crossed_ranks)
-}
\ No newline at end of file
+}
+
+// Highest rank at or below the given points, thresholds sorted ascending
+fn rank_for(thresholds: &[(i64, String)], points: i64) -> Option<&str> {
+    thresholds.iter()
+        .rev()
+        .find(|(threshold, _)| *threshold <= points)
+        .map(|(_, name)| name.as_str())
+}
+
+/// Snapshot of rank_thresholds, sorted by points ascending. Take one of these before changing
+/// the table so `reevaluate_ranks` can tell who moved.
+pub async fn rank_thresholds(db: &SqlitePool) -> Result<Vec<(i64, String)>> {
+    Ok(sqlx::query!("SELECT points, role_name FROM rank_thresholds ORDER BY points ASC")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.points, r.role_name))
+        .collect())
+}
+
+/// After rank_thresholds has been edited, re-checks every user's rank against the new table,
+/// fixes up the roles of everyone whose rank changed without their points moving (taking away
+/// the role of a removed or renamed rank along the way), and posts a
+/// summary to the mod channel.
+/// Returns how many users changed rank.
+pub async fn reevaluate_ranks(
+    ctx: &Context,
+    old_thresholds: &[(i64, String)],
+    db: &SqlitePool,
+) -> Result<usize> {
+    let new_thresholds = rank_thresholds(db).await?;
+    // Ranks that were removed or renamed - nothing points at their roles anymore, but whoever
+    // held them still has them
+    let retired_roles: Vec<String> = old_thresholds.iter()
+        .filter(|(_, name)| !new_thresholds.iter().any(|(_, new_name)| new_name == name))
+        .map(|(_, name)| name.clone())
+        .collect();
+
+    let users = sqlx::query!("SELECT discord_id, points FROM users")
+        .fetch_all(db)
+        .await?;
+
+    let mut changes = Vec::new();
+    for user in users {
+        let Some(discord_id) = user.discord_id else { continue };
+        let old_rank = rank_for(old_thresholds, user.points);
+        let new_rank = rank_for(&new_thresholds, user.points);
+        if old_rank == new_rank {
+            continue;
+        }
+        // One bad row shouldn't stop everyone else's roles being fixed
+        let Ok(user_id) = discord_id.parse::<u64>().map(UserId::new) else {
+            warn!("Skipping rank reevaluation for invalid Discord ID {}", discord_id);
+            continue;
+        };
+
+        let role_sync = sync_rank_role(ctx, &discord_id, user.points, &retired_roles, db).await.unwrap_or_else(|why| {
+            tracing::error!("Failed to sync rank roles for {}: {:?}", discord_id, why);
+            RoleSync::NeedsMod
+        });
+        let mut line = MessageBuilder::new();
+        line.push("• ")
+            .mention(&user_id)
+            .push(format!(
+                " ({}): {} → {}",
+                format_points(user.points),
+                old_rank.unwrap_or("Unranked"),
+                new_rank.unwrap_or("Unranked")
+            ));
+        if role_sync == RoleSync::NeedsMod {
+            line.push(" (needs the role changed by hand)");
+        }
+        changes.push(line.build());
+    }
+
+    if !changes.is_empty() {
+        let data = ctx.data.read().await;
+        if let Some(config) = data.get::<ConfigKey>() {
+            // Discord caps messages at 2000 characters, so split the list up if it gets long
+            let mut message = String::from("📋 **Rank Thresholds Changed**\nThe following users have a new rank:");
+            for change in &changes {
+                if message.len() + change.len() > 1900 {
+                    if let Err(why) = config.mod_channel_id.say(&ctx.http, &message).await {
+                        tracing::error!("Failed to send rank reevaluation summary: {:?}", why);
+                    }
+                    message.clear();
+                }
+                message.push('\n');
+                message.push_str(change);
+            }
+            if let Err(why) = config.mod_channel_id.say(&ctx.http, &message).await {
+                tracing::error!("Failed to send rank reevaluation summary: {:?}", why);
+            }
+        }
+    }
+
+    Ok(changes.len())
+}
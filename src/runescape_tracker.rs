@@ -7,6 +7,7 @@ use std::sync::Arc;
 use lazy_static::lazy_static;
 use tracing::{info, warn, error, debug};
 use crate::rank_manager;
+use crate::scoring;
 
 lazy_static! {
     // Regular expressions for parsing messages from RuneLite plugins
@@ -169,11 +170,21 @@ impl RunescapeTracker {
         }
 
         debug!("Found {} Discord accounts linked to RS name '{}'", discord_ids.len(), rs_name);
+
+        let item_id = match ctx.data.read().await.get::<crate::command_handler::PriceManagerKey>() {
+            Some(price_manager) => price_manager.get_item_id(item_name).await,
+            None => None,
+        };
         
         // Process drop for each linked Discord account
         for discord_id in discord_ids {
             debug!("Processing drop for Discord ID: {}", discord_id);
             
+            let points = scoring::drop_points(db, item_name, value).await?;
+
+            // The drop and its points go in together, so there's never one without the other
+            let mut tx = db.begin().await?;
+
             // Insert or update user
             sqlx::query!(
                 "INSERT INTO users (discord_id, points, total_drops) 
@@ -181,19 +192,22 @@ impl RunescapeTracker {
                  ON CONFLICT(discord_id) DO NOTHING",
                 discord_id
             )
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
 
             // Record the drop
-            sqlx::query!(
-                "INSERT INTO drops (discord_id, item_name, value, quantity) VALUES (?, ?, ?, ?)",
+            let drop_id = sqlx::query!(
+                "INSERT INTO drops (discord_id, item_name, value, quantity, points, item_id) VALUES (?, ?, ?, ?, ?, ?)",
                 discord_id,
                 item_name,
                 value,
-                quantity
+                quantity,
+                points,
+                item_id
             )
-            .execute(db)
-            .await?;
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
 
             // Update total drops
             sqlx::query!(
@@ -203,27 +217,27 @@ impl RunescapeTracker {
                 quantity,
                 discord_id
             )
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
 
-            // Calculate points (1 point per 100K gp)
-            let points = value / 100_000;
-            
+            debug!("Adding {} points to {}", points, discord_id);
+            let (old_points, new_points) = rank_manager::record_points(
+                &mut tx,
+                &discord_id,
+                points,
+                "runelite_drop",
+                Some(&format!("drop #{}", drop_id))
+            ).await?;
+            tx.commit().await?;
+
             // Get user name for rank updates
             let user_name = match self.get_username_from_discord_id(ctx, &discord_id).await {
                 Ok(name) => name,
                 Err(_) => format!("Unknown ({})", discord_id),
             };
 
-            // Add points and check for rank up
-            debug!("Adding {} points to {} ({})", points, user_name, discord_id);
-            rank_manager::add_points(
-                ctx,
-                &discord_id,
-                &user_name,
-                points,
-                db
-            ).await?;
+            // Check for rank up
+            rank_manager::finish_points_update(ctx, &discord_id, &user_name, old_points, new_points, db).await?;
             
             debug!("Auto-added drop for {}: {}x {} worth {} GP (Discord ID: {})", 
                   rs_name, quantity, item_name, value, discord_id);
@@ -272,8 +286,15 @@ impl RunescapeTracker {
             }
         };
         
+        // RuneLite only gives us the name. If several items share it, take the lowest id, same as the
+        // item id backfill
+        let Some(item) = collection_log_manager.find_items(item_name).await.into_iter().next() else {
+            warn!("Could not find clog item: {}", item_name);
+            return Ok(());
+        };
+
         // Calculate collection log points
-        let points = match collection_log_manager.calculate_points(item_name).await {
+        let points = match collection_log_manager.calculate_points(item.item_id).await {
             Some(pts) => {
                 debug!("Calculated {} points for collection log item: {}", pts, item_name);
                 pts
@@ -291,9 +312,9 @@ impl RunescapeTracker {
             // Check if user already has this collection log entry
             if let Ok(Some(_)) = sqlx::query!(
                 "SELECT id FROM collection_log_entries 
-                WHERE discord_id = ? AND item_name = ?",
+                WHERE discord_id = ? AND item_id = ? AND deleted_at IS NULL",
                 discord_id,
-                item_name
+                item.item_id
             )
             .fetch_optional(db)
             .await
@@ -302,6 +323,9 @@ impl RunescapeTracker {
                 continue;
             }
             
+            // The entry and its points go in together, so there's never one without the other
+            let mut tx = db.begin().await?;
+
             // Insert or update user
             sqlx::query!(
                 "INSERT INTO users (discord_id, points, total_drops) 
@@ -309,18 +333,36 @@ impl RunescapeTracker {
                 ON CONFLICT(discord_id) DO NOTHING",
                 discord_id
             )
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
 
-            // Record the collection log entry
-            sqlx::query!(
-                "INSERT INTO collection_log_entries (discord_id, item_name, points) VALUES (?, ?, ?)",
+            // Record the collection log entry - if it got logged since the check above, the unique
+            // index turns this into a no-op
+            let inserted = sqlx::query!(
+                "INSERT INTO collection_log_entries (discord_id, item_name, points, item_id) VALUES (?, ?, ?, ?)
+                ON CONFLICT(discord_id, item_id) WHERE deleted_at IS NULL DO NOTHING",
                 discord_id,
                 item_name,
-                points
+                points,
+                item.item_id
             )
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
+            if inserted.rows_affected() == 0 {
+                debug!("User {} already has collection log entry for {}", discord_id, item_name);
+                continue;
+            }
+            let entry_id = inserted.last_insert_rowid();
+
+            debug!("Adding {} points to {} for collection log item", points, discord_id);
+            let (old_points, new_points) = rank_manager::record_points(
+                &mut tx,
+                &discord_id,
+                points,
+                "runelite_clog",
+                Some(&format!("clog #{}", entry_id))
+            ).await?;
+            tx.commit().await?;
             
             // Get user name for rank updates
             let user_name = match self.get_username_from_discord_id(ctx, &discord_id).await {
@@ -328,15 +370,8 @@ impl RunescapeTracker {
                 Err(_) => format!("Unknown ({})", discord_id),
             };
 
-            // Add points and check for rank up
-            debug!("Adding {} points to {} ({}) for collection log item", points, user_name, discord_id);
-            rank_manager::add_points(
-                ctx,
-                &discord_id,
-                &user_name,
-                points,
-                db
-            ).await?;
+            // Check for rank up
+            rank_manager::finish_points_update(ctx, &discord_id, &user_name, old_points, new_points, db).await?;
             
             debug!("Auto-added collection log entry for {}: {} (+{} points) (Discord ID: {})", 
                   rs_name, item_name, points, discord_id);
@@ -0,0 +1,196 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use sqlx::SqlitePool;
+
+// What point_rules held before it existed, used if the '*' row has gone missing
+const DEFAULT_GP_PER_POINT: i64 = 100_000;
+const DEFAULT_MIN_VALUE: i64 = 100_000;
+
+/// How one item's drops are scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointRule {
+    pub gp_per_point: i64,
+    pub min_value: i64,
+    pub max_points: Option<i64>,
+}
+
+impl PointRule {
+    /// Whether a drop is worth enough to count at all
+    pub fn qualifies(&self, value: i64) -> bool {
+        value >= self.min_value
+    }
+
+    /// Points for a drop worth `value` gp (the whole stack, same as drops.value)
+    pub fn points(&self, value: i64) -> i64 {
+        if !self.qualifies(value) {
+            return 0;
+        }
+        let points = value / self.gp_per_point;
+        match self.max_points {
+            Some(max) => points.min(max),
+            None => points,
+        }
+    }
+}
+
+/// An item's override from point_rules; anything left NULL falls back to the default. A
+/// max_points of 0 is stored as is and means no cap for this item, even if the default has one.
+#[derive(Debug, Clone)]
+pub struct PointOverride {
+    pub item_name: String,
+    pub gp_per_point: Option<i64>,
+    pub min_value: Option<i64>,
+    pub max_points: Option<i64>,
+}
+
+/// Everything in point_rules. Every drop - /drop, the RuneLite tracker, Dink, /recalculate -
+/// gets scored through this, so they can't drift apart.
+pub struct PointRules {
+    pub default: PointRule,
+    pub overrides: HashMap<String, PointOverride>,
+}
+
+impl Default for PointRules {
+    fn default() -> Self {
+        Self {
+            default: PointRule {
+                gp_per_point: DEFAULT_GP_PER_POINT,
+                min_value: DEFAULT_MIN_VALUE,
+                max_points: None,
+            },
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl PointRules {
+    pub async fn load(db: &SqlitePool) -> Result<Self> {
+        let rows = sqlx::query!(
+            r#"SELECT item_name as "item_name!", gp_per_point, min_value, max_points FROM point_rules"#
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut rules = Self::default();
+        for row in rows {
+            if row.item_name == "*" {
+                rules.default = PointRule {
+                    gp_per_point: row.gp_per_point.unwrap_or(DEFAULT_GP_PER_POINT),
+                    min_value: row.min_value.unwrap_or(DEFAULT_MIN_VALUE),
+                    // 0 and NULL both mean no cap here - there's nothing to inherit from
+                    max_points: row.max_points.filter(|max| *max > 0),
+                };
+            } else {
+                rules.overrides.insert(row.item_name.to_lowercase(), PointOverride {
+                    item_name: row.item_name,
+                    gp_per_point: row.gp_per_point,
+                    min_value: row.min_value,
+                    max_points: row.max_points,
+                });
+            }
+        }
+
+        Ok(rules)
+    }
+
+    pub fn for_item(&self, item_name: &str) -> PointRule {
+        match self.overrides.get(&item_name.to_lowercase()) {
+            Some(item) => PointRule {
+                gp_per_point: item.gp_per_point.unwrap_or(self.default.gp_per_point),
+                min_value: item.min_value.unwrap_or(self.default.min_value),
+                max_points: match item.max_points {
+                    None => self.default.max_points,
+                    Some(0) => None,
+                    Some(max) => Some(max),
+                },
+            },
+            None => self.default,
+        }
+    }
+}
+
+/// Shorthand for scoring a single drop
+pub async fn drop_points(db: &SqlitePool, item_name: &str, value: i64) -> Result<i64> {
+    Ok(PointRules::load(db).await?.for_item(item_name).points(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, gp_per_point: Option<i64>, min_value: Option<i64>, max_points: Option<i64>) -> PointOverride {
+        PointOverride {
+            item_name: name.to_string(),
+            gp_per_point,
+            min_value,
+            max_points,
+        }
+    }
+
+    fn rules(default_max: Option<i64>, overrides: Vec<PointOverride>) -> PointRules {
+        PointRules {
+            default: PointRule {
+                gp_per_point: 100_000,
+                min_value: 100_000,
+                max_points: default_max,
+            },
+            overrides: overrides.into_iter()
+                .map(|item| (item.item_name.to_lowercase(), item))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn drops_below_min_value_score_nothing() {
+        let rule = rules(None, vec![]).default;
+        assert!(!rule.qualifies(99_999));
+        assert_eq!(rule.points(99_999), 0);
+        assert!(rule.qualifies(100_000));
+        assert_eq!(rule.points(100_000), 1);
+    }
+
+    #[test]
+    fn points_round_down() {
+        let rule = rules(None, vec![]).default;
+        assert_eq!(rule.points(199_999), 1);
+        assert_eq!(rule.points(200_000), 2);
+        assert_eq!(rule.points(1_250_000_000), 12_500);
+    }
+
+    #[test]
+    fn max_points_caps_a_drop() {
+        let rule = rules(Some(50), vec![]).default;
+        assert_eq!(rule.points(4_900_000), 49);
+        assert_eq!(rule.points(5_000_000), 50);
+        assert_eq!(rule.points(500_000_000), 50);
+    }
+
+    #[test]
+    fn unknown_items_use_the_default() {
+        let rules = rules(Some(50), vec![item("Twisted bow", Some(1_000_000), None, None)]);
+        assert_eq!(rules.for_item("Abyssal whip"), rules.default);
+    }
+
+    #[test]
+    fn overrides_match_case_insensitively_and_inherit_unset_fields() {
+        let rules = rules(Some(50), vec![item("Twisted bow", Some(1_000_000), None, None)]);
+        let rule = rules.for_item("TWISTED BOW");
+        assert_eq!(rule.gp_per_point, 1_000_000);
+        assert_eq!(rule.min_value, 100_000);
+        assert_eq!(rule.max_points, Some(50));
+    }
+
+    #[test]
+    fn override_max_points_zero_uncaps_the_item() {
+        let rules = rules(Some(50), vec![item("Twisted bow", None, None, Some(0))]);
+        let rule = rules.for_item("Twisted bow");
+        assert_eq!(rule.max_points, None);
+        assert_eq!(rule.points(1_000_000_000), 10_000);
+    }
+
+    #[test]
+    fn override_max_points_replaces_the_default_cap() {
+        let rules = rules(Some(50), vec![item("Twisted bow", None, None, Some(500))]);
+        assert_eq!(rules.for_item("Twisted bow").points(1_000_000_000), 500);
+    }
+}